@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+use anyhow::{anyhow, bail};
+use utils::Result;
+
+use crate::scanner::Scanner;
+use crate::vocabulary::{Match, Vocabulary};
+
+/// What to do with a line that holds no tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BadLines {
+    Fail,
+    /// Leave the line out of the total, with a warning on stderr.
+    Skip,
+}
+
+/// Add a line's calibration value to the running total.
+fn add(total: u64, value: u64) -> Result<u64> {
+    total
+        .checked_add(value)
+        .ok_or_else(|| anyhow!("total calibration value overflows u64"))
+}
+
+/// Sum the calibration values of every line.
+pub(crate) fn calibrate(input: &[String], vocab: &Vocabulary, bad: BadLines) -> Result<u64> {
+    let scanner = Scanner::new(vocab);
+    let mut total = 0;
+    for (i, line) in input.iter().enumerate() {
+        match (scanner.calibrate(line), bad) {
+            (Ok(value), _) => total = add(total, value)?,
+            (Err(e), BadLines::Fail) => return Err(e),
+            (Err(e), BadLines::Skip) => eprintln!("warning: skipping line {}: {e}", i + 1),
+        }
+    }
+    Ok(total)
+}
+
+fn describe(m: &Match) -> String {
+    let kind = if m.token.bytes().all(|b| b.is_ascii_digit()) {
+        "digit"
+    } else {
+        "word"
+    };
+    format!("{:?} at {} ({kind})", m.token, m.start)
+}
+
+/// One line per input line, showing how it was decoded.
+///
+/// Lines with a single token or overlapping tokens are flagged, since those
+/// are where answers tend to be disputed. Lines with no tokens are listed
+/// too and left out of the total, unless `bad` says to fail on the first
+/// one.
+pub(crate) fn report(input: &[String], vocab: &Vocabulary, bad: BadLines) -> Result<String> {
+    let scanner = Scanner::new(vocab);
+    let width = input.len().to_string().len();
+    let mut out = String::new();
+    let mut total = 0;
+    let mut bad_lines = 0;
+
+    for (i, line) in input.iter().enumerate() {
+        let n = i + 1;
+        let Some(scan) = scanner.scan(line) else {
+            if bad == BadLines::Fail {
+                bail!("line {n}: no tokens in {line:?}");
+            }
+            writeln!(out, "{n:>width$}: no tokens in {line:?}")?;
+            bad_lines += 1;
+            continue;
+        };
+
+        total = add(total, scan.value())?;
+        write!(
+            out,
+            "{n:>width$}: {:>3}  first {}, last {}",
+            scan.value(),
+            describe(&scan.first),
+            describe(&scan.last)
+        )?;
+        if scan.tokens == 1 {
+            out.push_str("  [single token]");
+        }
+        if scan.overlapping {
+            out.push_str("  [overlapping]");
+        }
+        out.push('\n');
+    }
+
+    if bad_lines > 0 {
+        eprintln!("warning: skipped {bad_lines} lines with no tokens");
+    }
+    writeln!(out, "total: {total}")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(ToOwned::to_owned).collect()
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let input = lines("two1nine\nxtwone3four\ntreb7uchet\nnothing");
+
+        let out = report(&input, &Vocabulary::english(), BadLines::Skip)?;
+        let expected = [
+            "1:  29  first \"two\" at 0 (word), last \"nine\" at 4 (word)",
+            "2:  24  first \"two\" at 1 (word), last \"four\" at 7 (word)  [overlapping]",
+            "3:  77  first \"7\" at 4 (digit), last \"7\" at 4 (digit)  [single token]",
+            "4: no tokens in \"nothing\"",
+            "total: 130",
+        ];
+        assert_eq!(out.lines().collect::<Vec<_>>(), expected);
+
+        let err = report(&input, &Vocabulary::english(), BadLines::Fail).unwrap_err();
+        assert_eq!(err.to_string(), "line 4: no tokens in \"nothing\"");
+
+        assert!(calibrate(&input, &Vocabulary::english(), BadLines::Fail).is_err());
+        assert_eq!(
+            calibrate(&input, &Vocabulary::english(), BadLines::Skip)?,
+            130
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow() -> Result<()> {
+        // Each line is worth 11 * u32::MAX, so u64 overflows after about
+        // 390 million of them; start the total near the limit instead.
+        let vocab = Vocabulary::from_spec(&format!("big={}", u32::MAX))?;
+        let line = lines("big");
+        assert_eq!(
+            calibrate(&line, &vocab, BadLines::Fail)?,
+            11 * u32::MAX as u64
+        );
+
+        let err = add(u64::MAX - 5, 6).unwrap_err();
+        assert_eq!(err.to_string(), "total calibration value overflows u64");
+        assert_eq!(add(u64::MAX - 5, 5)?, u64::MAX);
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
-use anyhow::anyhow;
-use regex::Regex;
+mod diagnose;
+mod scanner;
+mod vocabulary;
+
+use diagnose::{calibrate, BadLines};
 use utils::{Part1, Part2, Result};
+use vocabulary::Vocabulary;
 
 struct Problem1 {}
 
@@ -13,75 +17,48 @@ impl Part1 for Problem1 {
     }
 
     fn run1(input: Self::Input) -> Result<String> {
-        let nums = input
-            .iter()
-            .map(|line| {
-                let nums: Vec<_> = line
-                    .chars()
-                    .filter_map(|x| match x {
-                        '0'..='9' => Some((x as u8 - '0' as u8) as u32),
-                        _ => None,
-                    })
-                    .collect();
-
-                match (nums.first(), nums.last()) {
-                    (Some(&x), Some(&y)) => Ok(10 * x + y),
-                    _ => Err(anyhow!("Invalid line: {line}")),
-                }
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        Ok(nums.iter().sum::<u32>().to_string())
+        Ok(calibrate(&input, &Vocabulary::digits(), BadLines::Fail)?.to_string())
     }
 }
 
 impl Part2 for Problem1 {
     fn run2(input: Self::Input) -> Result<String> {
-        // regexes for getting the first and last occurrence of required
-        // pattern.
-        let match_first = Regex::new(r"([0-9]|one|two|three|four|five|six|seven|eight|nine).*$")?;
-        let match_last = Regex::new(r"^.*([0-9]|one|two|three|four|five|six|seven|eight|nine)")?;
-
-        let match2num = |s: &str| match s {
-            "0" => 0,
-            "1" | "one" => 1,
-            "2" | "two" => 2,
-            "3" | "three" => 3,
-            "4" | "four" => 4,
-            "5" | "five" => 5,
-            "6" | "six" => 6,
-            "7" | "seven" => 7,
-            "8" | "eight" => 8,
-            "9" | "nine" => 9,
-            s => panic!("Failed to parse line: {s}"),
-        };
-
-        let get_match = |line, re: &Regex| {
-            re.captures(line).map(|c| {
-                // unwrap() is safe here b/c regex captures have at
-                // least one capture grou.
-                let capture = c.get(1).unwrap().as_str();
-                match2num(capture)
-            })
-        };
-
-        let nums: Vec<u32> = input
-            .iter()
-            .map(|line| {
-                let first = get_match(line, &match_first);
-                let last = get_match(line, &match_last);
-                match (first, last) {
-                    (Some(x), Some(y)) => Ok(10 * x + y),
-                    _ => Err(anyhow!("Invalid line: {line}")),
-                }
-            })
-            .collect::<Result<_>>()?;
-
-        Ok(nums.iter().sum::<u32>().to_string())
+        Ok(calibrate(&input, &Vocabulary::english(), BadLines::Fail)?.to_string())
     }
 }
 
 fn main() -> Result<()> {
+    let bad = if utils::has_flag("--skip-bad") {
+        BadLines::Skip
+    } else {
+        BadLines::Fail
+    };
+    let vocab = utils::flag_value("--vocab")
+        .map(|spec| Vocabulary::from_spec(&spec))
+        .transpose()?;
+
+    if utils::has_flag("--diagnose") {
+        let vocab = vocab.unwrap_or_else(Vocabulary::english);
+        print!(
+            "{}",
+            diagnose::report(&utils::load::<Problem1>()?, &vocab, bad)?
+        );
+        return Ok(());
+    }
+    if let Some(vocab) = vocab {
+        println!("{}", calibrate(&utils::load::<Problem1>()?, &vocab, bad)?);
+        return Ok(());
+    }
+    if bad == BadLines::Skip {
+        let input = utils::load::<Problem1>()?;
+        println!("Part 1: {}", calibrate(&input, &Vocabulary::digits(), bad)?);
+        println!(
+            "Part 2: {}",
+            calibrate(&input, &Vocabulary::english(), bad)?
+        );
+        return Ok(());
+    }
+
     utils::run_part1::<Problem1>()?;
     utils::run_part2::<Problem1>()
 }
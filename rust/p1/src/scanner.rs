@@ -0,0 +1,186 @@
+use crate::vocabulary::{Match, Vocabulary};
+
+/// An Aho-Corasick automaton over a vocabulary's tokens.
+///
+/// Every state has a transition for every byte, so scanning a line is one
+/// table lookup per byte, and finding both the first and last token takes a
+/// single pass with no allocation.
+#[derive(Debug, Clone)]
+pub(crate) struct Scanner {
+    tokens: Vec<(String, u32)>,
+    states: Vec<State>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    next: [u32; 256],
+    /// Tokens ending at this state, including those that are suffixes of
+    /// longer tokens.
+    out: Vec<usize>,
+}
+
+/// The first and last tokens on a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Scan<'a> {
+    pub first: Match<'a>,
+    pub last: Match<'a>,
+    /// How many tokens the line holds in all, overlaps included.
+    pub tokens: usize,
+    /// Whether any two tokens share a byte, as in `twone`.
+    pub overlapping: bool,
+}
+
+impl Scanner {
+    pub fn new(vocab: &Vocabulary) -> Scanner {
+        let tokens = vocab.tokens().to_vec();
+        let mut states = vec![State { next: [0; 256], out: vec![] }];
+
+        // The trie, with 0 meaning "no edge" since nothing leads back to the
+        // root.
+        for (t, (token, _)) in tokens.iter().enumerate() {
+            let mut s = 0;
+            for &b in token.as_bytes() {
+                if states[s].next[b as usize] == 0 {
+                    states.push(State { next: [0; 256], out: vec![] });
+                    states[s].next[b as usize] = (states.len() - 1) as u32;
+                }
+                s = states[s].next[b as usize] as usize;
+            }
+            states[s].out.push(t);
+        }
+
+        // Breadth first, fill each missing edge with the edge from the
+        // state's failure link, which is always shallower and so already
+        // complete.
+        let mut fail = vec![0usize; states.len()];
+        let mut queue: std::collections::VecDeque<usize> = states[0]
+            .next
+            .iter()
+            .filter(|&&n| n != 0)
+            .map(|&n| n as usize)
+            .collect();
+        while let Some(s) = queue.pop_front() {
+            let inherited = states[fail[s]].out.clone();
+            states[s].out.extend(inherited);
+            for b in 0..256 {
+                let child = states[s].next[b] as usize;
+                let via_fail = states[fail[s]].next[b];
+                if child == 0 {
+                    states[s].next[b] = via_fail;
+                } else {
+                    fail[child] = via_fail as usize;
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        Scanner { tokens, states }
+    }
+
+    /// Find the first and last tokens in one pass.
+    ///
+    /// Tokens may overlap: in `twone` the first token is `two` and the last
+    /// is `one`. The first token is the one starting earliest and the last
+    /// the one starting latest; if several start at the same place the
+    /// longest wins, so `seventeen` beats `seven`.
+    pub fn scan(&self, line: &str) -> Option<Scan<'_>> {
+        let mut first: Option<(usize, usize)> = None;
+        let mut last: Option<(usize, usize)> = None;
+        let mut tokens = 0;
+        let mut overlapping = false;
+        // Tokens are found in order of where they end, so one overlaps an
+        // earlier one exactly when it starts before the furthest end so far.
+        let mut furthest_end = 0;
+
+        let mut s = 0;
+        for (i, &b) in line.as_bytes().iter().enumerate() {
+            s = self.states[s].next[b as usize] as usize;
+            for &t in self.states[s].out.iter() {
+                let len = self.tokens[t].0.len();
+                let start = i + 1 - len;
+                tokens += 1;
+                overlapping |= start < furthest_end;
+                furthest_end = i + 1;
+                let longer = |(s, u): (usize, usize)| start == s && len > self.tokens[u].0.len();
+                if first.is_none_or(|f| start < f.0 || longer(f)) {
+                    first = Some((start, t));
+                }
+                if last.is_none_or(|l| start > l.0 || longer(l)) {
+                    last = Some((start, t));
+                }
+            }
+        }
+
+        let found = |(start, t): (usize, usize)| {
+            let (token, value) = &self.tokens[t];
+            Match { start, token, value: *value }
+        };
+        Some(Scan {
+            first: found(first?),
+            last: found(last?),
+            tokens,
+            overlapping,
+        })
+    }
+
+    /// The line's calibration value: ten times the first token plus the
+    /// last.
+    pub fn calibrate(&self, line: &str) -> utils::Result<u64> {
+        let scan = self
+            .scan(line)
+            .ok_or_else(|| anyhow::anyhow!("Invalid line: {line}"))?;
+        Ok(scan.value())
+    }
+}
+
+impl Scan<'_> {
+    /// The calibration value: ten times the first token plus the last.
+    /// Token values are `u32`, so this always fits.
+    pub fn value(&self) -> u64 {
+        10 * u64::from(self.first.value) + u64::from(self.last.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches_vocabulary() -> utils::Result<()> {
+        let vocab = Vocabulary::from_spec("digits,ones,zero,teens,tens,german,french")?;
+        let scanner = Scanner::new(&vocab);
+
+        let lines = [
+            "twone",
+            "xtwone3four",
+            "seventeenineight",
+            "eighteeninety",
+            "zweinsechseventy",
+            "fünfzero",
+            "nothing here",
+            "",
+            "1",
+            "sixteensixtyseventeen",
+            "deuxunhuit",
+        ];
+        for line in lines {
+            let expected = vocab.first(line).zip(vocab.last(line));
+            let scan = scanner.scan(line).map(|scan| (scan.first, scan.last));
+            assert_eq!(scan, expected, "{line}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_counts() {
+        let scanner = Scanner::new(&Vocabulary::english());
+        let counts = |line| {
+            let scan = scanner.scan(line).unwrap();
+            (scan.tokens, scan.overlapping)
+        };
+        assert_eq!(counts("7pqrstsixteen"), (2, false));
+        assert_eq!(counts("xtwone3four"), (4, true));
+        assert_eq!(counts("eightwothree"), (3, true));
+        assert_eq!(counts("treb7uchet"), (1, false));
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{anyhow, bail, ensure};
+use utils::Result;
+
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+const ONES: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+const TEENS: [&str; 10] = [
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TENS: [&str; 8] = [
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const GERMAN: [&str; 9] = [
+    "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+];
+const FRENCH: [&str; 9] = [
+    "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+];
+
+/// The tokens that count as numbers on a calibration line, and their
+/// values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Vocabulary {
+    tokens: Vec<(String, u32)>,
+}
+
+/// A token found on a line, starting at byte offset `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Match<'a> {
+    pub start: usize,
+    pub token: &'a str,
+    pub value: u32,
+}
+
+impl Vocabulary {
+    /// Just the digits `0` to `9`.
+    pub fn digits() -> Vocabulary {
+        let mut vocab = Vocabulary::default();
+        vocab.add_all(&DIGITS, 0).unwrap();
+        vocab
+    }
+
+    /// The digits plus `one` to `nine`.
+    pub fn english() -> Vocabulary {
+        Vocabulary::digits().with_preset("ones").unwrap()
+    }
+
+    /// Add a named group of words:
+    ///
+    /// - `digits`: `0` to `9`
+    /// - `ones`: `one` to `nine`
+    /// - `zero`
+    /// - `teens`: `ten` to `nineteen`, worth 10 to 19
+    /// - `tens`: `twenty` to `ninety`, worth 20 to 90
+    /// - `german`: `eins` to `neun`
+    /// - `french`: `un` to `neuf`
+    pub fn with_preset(mut self, name: &str) -> Result<Vocabulary> {
+        match name {
+            "digits" => self.add_all(&DIGITS, 0)?,
+            "ones" => self.add_all(&ONES, 1)?,
+            "zero" => self.add("zero", 0)?,
+            "teens" => self.add_all(&TEENS, 10)?,
+            "tens" => {
+                for (i, word) in TENS.iter().enumerate() {
+                    self.add(word, 20 + 10 * i as u32)?;
+                }
+            }
+            "german" => self.add_all(&GERMAN, 1)?,
+            "french" => self.add_all(&FRENCH, 1)?,
+            _ => bail!("unknown vocabulary {name:?}"),
+        }
+        Ok(self)
+    }
+
+    /// Build a vocabulary from a comma-separated list of presets (see
+    /// [`Vocabulary::with_preset`]) and `token=value` pairs, like
+    /// `digits,ones,german,dozen=12`.
+    pub fn from_spec(spec: &str) -> Result<Vocabulary> {
+        let mut vocab = Vocabulary::default();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item.split_once('=') {
+                Some((token, value)) => {
+                    let value = value
+                        .parse()
+                        .map_err(|e| anyhow!("bad value for token {token:?}: {e}"))?;
+                    vocab.add(token, value)?;
+                }
+                None => vocab = vocab.with_preset(item)?,
+            }
+        }
+        ensure!(!vocab.tokens.is_empty(), "empty vocabulary {spec:?}");
+        Ok(vocab)
+    }
+
+    /// Add a single token. Adding the same token twice is fine as long as
+    /// it keeps its value.
+    pub fn add(&mut self, token: &str, value: u32) -> Result<()> {
+        ensure!(!token.is_empty(), "empty token");
+        match self.value(token) {
+            Ok(old) if old == value => {}
+            Ok(old) => bail!("token {token:?} is already worth {old}, not {value}"),
+            Err(_) => self.tokens.push((token.to_string(), value)),
+        }
+        Ok(())
+    }
+
+    fn add_all(&mut self, words: &[&str], first: u32) -> Result<()> {
+        for (i, word) in words.iter().enumerate() {
+            self.add(word, first + i as u32)?;
+        }
+        Ok(())
+    }
+
+    /// The value of a token.
+    pub fn value(&self, token: &str) -> Result<u32> {
+        self.tokens
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|&(_, v)| v)
+            .ok_or_else(|| anyhow!("unknown token {token:?}"))
+    }
+
+    pub fn tokens(&self) -> &[(String, u32)] {
+        &self.tokens
+    }
+
+    /// The longest token starting at byte offset `start`.
+    #[cfg(test)]
+    fn at<'a>(&'a self, line: &str, start: usize) -> Option<Match<'a>> {
+        let rest = line.get(start..)?;
+        self.tokens
+            .iter()
+            .filter(|(token, _)| rest.starts_with(token.as_str()))
+            .max_by_key(|(token, _)| token.len())
+            .map(|(token, value)| Match { start, token, value: *value })
+    }
+
+    /// The token that starts earliest on the line, found by trying every
+    /// token at every offset. This is the reference for [`Scanner::scan`].
+    ///
+    /// [`Scanner::scan`]: crate::scanner::Scanner::scan
+    #[cfg(test)]
+    pub fn first<'a>(&'a self, line: &str) -> Option<Match<'a>> {
+        (0..line.len()).find_map(|i| self.at(line, i))
+    }
+
+    /// The token that starts latest on the line, found the same way as
+    /// [`Vocabulary::first`].
+    #[cfg(test)]
+    pub fn last<'a>(&'a self, line: &str) -> Option<Match<'a>> {
+        (0..line.len()).rev().find_map(|i| self.at(line, i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_overlaps() -> Result<()> {
+        let english = Vocabulary::english();
+        let first = english.first("xtwone").unwrap();
+        let last = english.last("xtwone").unwrap();
+        assert_eq!(first, Match { start: 1, token: "two", value: 2 });
+        assert_eq!(last, Match { start: 3, token: "one", value: 1 });
+        assert_eq!(Scanner::new(&english).calibrate("twone")?, 21);
+        assert_eq!(Scanner::new(&english).calibrate("eightwo")?, 82);
+
+        let teens = Vocabulary::english().with_preset("teens")?;
+        assert_eq!(teens.first("seventeen").unwrap().value, 17);
+        assert_eq!(Scanner::new(&teens).calibrate("seventeen")?, 187);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec() -> Result<()> {
+        let vocab = Vocabulary::from_spec("digits,german,french,zero,dozen=12")?;
+        let scanner = Scanner::new(&vocab);
+        assert_eq!(scanner.calibrate("xeinsfünf")?, 15);
+        assert_eq!(scanner.calibrate("neufzero")?, 90);
+        assert_eq!(scanner.calibrate("adozen")?, 132);
+        assert_eq!(vocab.value("dozen")?, 12);
+
+        let err = vocab.value("seven").unwrap_err();
+        assert_eq!(err.to_string(), "unknown token \"seven\"");
+
+        let err = Vocabulary::from_spec("klingon").unwrap_err();
+        assert_eq!(err.to_string(), "unknown vocabulary \"klingon\"");
+
+        let err = Vocabulary::from_spec("ones,one=2").unwrap_err();
+        assert_eq!(err.to_string(), "token \"one\" is already worth 1, not 2");
+
+        let err = Scanner::new(&Vocabulary::english())
+            .calibrate("abc")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid line: abc");
+
+        Ok(())
+    }
+}
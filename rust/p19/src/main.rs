@@ -24,7 +24,7 @@ impl utils::Part1 for Problem19 {
                 pos = match workflows.get(&target) {
                     Some(rules) => {
                         let Some(pos) = rules.iter().find_map(|rule| {
-                            if rule.matches(&part) {
+                            if rule.matches(part) {
                                 Some(rule.dest.clone())
                             } else {
                                 None
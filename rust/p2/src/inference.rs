@@ -0,0 +1,183 @@
+//! Estimating what's in the bag from the games played with it.
+//!
+//! Each sample is modelled as a handful of cubes drawn from the bag without
+//! replacement, with the cubes put back before the next sample.
+
+use crate::{CubeSet, Game};
+
+/// The smallest bag consistent with every game.
+pub(crate) fn minimal_bag(games: &[Game]) -> CubeSet {
+    games.iter().fold(CubeSet::default(), |acc, game| {
+        acc.union(&game.minimal_bag())
+    })
+}
+
+/// `ln(n choose k)`, or negative infinity if `k > n`.
+fn ln_choose(n: u32, k: u32) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    let k = k.min(n - k);
+    (0..k)
+        .map(|i| f64::from(n - i).ln() - f64::from(i + 1).ln())
+        .sum()
+}
+
+/// The log probability of drawing exactly `sample` in one handful from
+/// `bag`: the multivariate hypergeometric distribution. Negative infinity if
+/// the bag can't produce it.
+pub(crate) fn sample_log_likelihood(bag: &CubeSet, sample: &CubeSet) -> f64 {
+    if !sample.consistent_with(bag) {
+        return f64::NEG_INFINITY;
+    }
+    let ways: f64 = sample
+        .counts
+        .iter()
+        .map(|(color, &count)| ln_choose(bag.get(color), count))
+        .sum();
+    ways - ln_choose(bag.total(), sample.total())
+}
+
+/// The log probability of every sample in `game` coming from `bag`.
+pub(crate) fn log_likelihood(bag: &CubeSet, game: &Game) -> f64 {
+    game.samples
+        .iter()
+        .map(|sample| sample_log_likelihood(bag, sample))
+        .sum()
+}
+
+/// Every bag with between `min` and `min + extra` cubes of each of `min`'s
+/// colors.
+pub(crate) fn candidates(min: &CubeSet, extra: u32) -> Vec<CubeSet> {
+    min.counts
+        .iter()
+        .fold(vec![CubeSet::default()], |bags, (color, &count)| {
+            bags.iter()
+                .flat_map(|bag| {
+                    (count..=count + extra).map(move |n| {
+                        let mut bag = bag.clone();
+                        bag.set(color, n);
+                        bag
+                    })
+                })
+                .collect()
+        })
+}
+
+/// Candidate bags with their log likelihood over all of `games`, most
+/// likely first. Bags that can't have produced the games are dropped.
+pub(crate) fn rank(games: &[Game], candidates: Vec<CubeSet>) -> Vec<(CubeSet, f64)> {
+    let mut ranked: Vec<(CubeSet, f64)> = candidates
+        .into_iter()
+        .map(|bag| {
+            let ll = games.iter().map(|game| log_likelihood(&bag, game)).sum();
+            (bag, ll)
+        })
+        .filter(|(_, ll): &(CubeSet, f64)| ll.is_finite())
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+/// A small deterministic generator, so simulations can be repeated.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        // SplitMix64.
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Play `games` games of `samples` samples each from `bag`, each sample
+/// drawing between one and `max_draw` cubes.
+pub(crate) fn simulate(
+    bag: &CubeSet,
+    games: u32,
+    samples: usize,
+    max_draw: u32,
+    rng: &mut Rng,
+) -> Vec<Game> {
+    let mut cubes: Vec<&str> = bag
+        .counts
+        .iter()
+        .flat_map(|(color, &count)| std::iter::repeat_n(color.as_str(), count as usize))
+        .collect();
+    let max_draw = max_draw.min(cubes.len() as u32) as usize;
+    if max_draw == 0 {
+        return (1..=games).map(|id| Game { id, samples: vec![] }).collect();
+    }
+
+    (1..=games)
+        .map(|id| {
+            let samples = (0..samples)
+                .map(|_| {
+                    // A partial Fisher-Yates shuffle picks the handful.
+                    let draw = 1 + rng.below(max_draw);
+                    let mut sample = CubeSet::default();
+                    for i in 0..draw {
+                        let j = i + rng.below(cubes.len() - i);
+                        cubes.swap(i, j);
+                        sample.set(cubes[i], sample.get(cubes[i]) + 1);
+                    }
+                    sample
+                })
+                .collect();
+            Game { id, samples }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_likelihood() {
+        let bag = CubeSet::from([("red", 2), ("blue", 1)]);
+
+        // 2 of the 3 ways to pick two cubes give one of each.
+        let sample = CubeSet::from([("red", 1), ("blue", 1)]);
+        let p = sample_log_likelihood(&bag, &sample).exp();
+        assert!((p - 2.0 / 3.0).abs() < 1e-12, "{p}");
+
+        let sample = CubeSet::from([("red", 2)]);
+        let p = sample_log_likelihood(&bag, &sample).exp();
+        assert!((p - 1.0 / 3.0).abs() < 1e-12, "{p}");
+
+        let sample = CubeSet::from([("blue", 2)]);
+        assert_eq!(sample_log_likelihood(&bag, &sample), f64::NEG_INFINITY);
+        let sample = CubeSet::from([("green", 1)]);
+        assert_eq!(sample_log_likelihood(&bag, &sample), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_recovers_simulated_bag() {
+        let truth = CubeSet::from([("red", 4), ("green", 2), ("blue", 3)]);
+        let games = simulate(&truth, 400, 3, 5, &mut Rng::new(7));
+
+        let min = minimal_bag(&games);
+        assert!(min.consistent_with(&truth));
+        for game in games.iter() {
+            assert!(game.consistent_with(&truth));
+        }
+
+        let ranked = rank(&games, candidates(&min, 4));
+        assert_eq!(ranked[0].0, truth);
+        assert!(ranked.iter().all(|(bag, _)| min.consistent_with(bag)));
+    }
+}
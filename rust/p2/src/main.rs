@@ -1,6 +1,10 @@
+mod inference;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use regex::Regex;
+use anyhow::Context;
 use utils::{Part1, Part2, Result};
 
 struct Problem2 {}
@@ -10,38 +14,140 @@ impl Part1 for Problem2 {
     type Input = Input;
 
     fn parse(data: &str) -> Result<Self::Input> {
-        let parsed: Result<_> = data.lines().map(|line| line.parse::<Game>()).collect();
+        let parsed: Result<_> = data
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                line.parse::<Game>()
+                    .with_context(|| format!("line {}", i + 1))
+            })
+            .collect();
         parsed.map(|games| Input { games })
     }
 
     fn run1(input: Self::Input) -> Result<String> {
-        let possible_games = input
-            .games
-            .iter()
-            .filter(|game| game.consistent_with(CubeSet { red: 12, green: 13, blue: 14 }));
-
-        let output: u32 = possible_games.map(|g| g.id).sum();
-        Ok(output.to_string())
+        Ok(possible_id_sum(&input.games, &CubeSet::default_bag()).to_string())
     }
 }
 
 impl Part2 for Problem2 {
     fn run2(input: Self::Input) -> Result<String> {
-        let sum: u32 = input.games.iter().map(|g| g.power()).sum();
-        Ok(sum.to_string())
+        Ok(power_sum(&input.games, &CubeSet::default_bag())?.to_string())
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Sum the powers of every game, over the colors in `bag`.
+fn power_sum(games: &[Game], bag: &CubeSet) -> Result<u64> {
+    games.iter().try_fold(0u64, |total, game| {
+        total
+            .checked_add(game.power(bag)?)
+            .ok_or_else(|| anyhow::anyhow!("total power overflows u64"))
+    })
+}
+
+/// Sum the ids of the games that could have been played with `bag`.
+fn possible_id_sum(games: &[Game], bag: &CubeSet) -> u32 {
+    games
+        .iter()
+        .filter(|game| game.consistent_with(bag))
+        .map(|g| g.id)
+        .sum()
+}
+
+/// A count of cubes per color. Colors that aren't mentioned have no cubes.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
 struct CubeSet {
-    red: u32,
-    green: u32,
-    blue: u32,
+    /// Only nonzero counts are stored, so sets that differ only in explicit
+    /// zeros compare equal.
+    counts: BTreeMap<String, u32>,
 }
 
 impl CubeSet {
-    pub fn consistent_with(&self, other: CubeSet) -> bool {
-        self.red <= other.red && self.green <= other.green && self.blue <= other.blue
+    /// The bag from the puzzle: 12 red, 13 green and 14 blue cubes.
+    pub fn default_bag() -> CubeSet {
+        CubeSet::from([("red", 12), ("green", 13), ("blue", 14)])
+    }
+
+    pub fn get(&self, color: &str) -> u32 {
+        self.counts.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, color: &str, count: u32) {
+        if count == 0 {
+            self.counts.remove(color);
+        } else {
+            self.counts.insert(color.to_string(), count);
+        }
+    }
+
+    /// Whether every color has at most as many cubes here as in `other`.
+    pub fn consistent_with(&self, other: &CubeSet) -> bool {
+        self.counts
+            .iter()
+            .all(|(color, &count)| count <= other.get(color))
+    }
+
+    /// The number of cubes of every color together.
+    pub fn total(&self) -> u32 {
+        self.counts.values().sum()
+    }
+
+    /// The smallest set containing both `self` and `other`.
+    pub fn union(mut self, other: &CubeSet) -> CubeSet {
+        for (color, &count) in other.counts.iter() {
+            if count > self.get(color) {
+                self.set(color, count);
+            }
+        }
+        self
+    }
+}
+
+impl Display for CubeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(color, count)| format!("{color}={count}"))
+            .collect();
+        write!(f, "{}", counts.join(","))
+    }
+}
+
+impl<const N: usize> From<[(&str, u32); N]> for CubeSet {
+    fn from(counts: [(&str, u32); N]) -> CubeSet {
+        let mut set = CubeSet::default();
+        for (color, count) in counts {
+            set.set(color, count);
+        }
+        set
+    }
+}
+
+impl FromStr for CubeSet {
+    type Err = anyhow::Error;
+
+    /// Parse a bag like `red=12,green=13,blue=14`.
+    fn from_str(s: &str) -> Result<CubeSet> {
+        let mut set = CubeSet::default();
+        // Zero counts aren't stored, so duplicates are tracked separately.
+        let mut seen = BTreeSet::new();
+        for entry in s.split(',').map(str::trim) {
+            let Some((color, count)) = entry.split_once('=') else {
+                anyhow::bail!("Invalid bag entry {entry:?}, expected color=count");
+            };
+            let count = count
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid count in bag entry {entry:?}: {e}"))?;
+            if !seen.insert(color) {
+                anyhow::bail!("Color {color:?} appears twice in bag");
+            }
+            set.set(color, count);
+        }
+        if set.counts.is_empty() {
+            anyhow::bail!("Bag {s:?} has no cubes");
+        }
+        Ok(set)
     }
 }
 
@@ -52,77 +158,112 @@ struct Game {
 }
 
 impl Game {
-    pub fn power(&self) -> u32 {
-        let min = self
-            .samples
-            .iter()
-            .copied()
-            .reduce(|acc, next| CubeSet {
-                red: std::cmp::max(acc.red, next.red),
-                green: std::cmp::max(acc.green, next.green),
-                blue: std::cmp::max(acc.blue, next.blue),
+    /// The product of the smallest number of cubes of each of `bag`'s colors
+    /// that could have played this game. A color the game never draws needs
+    /// no cubes, which makes the power 0. Counts can be anything, so the
+    /// product is checked.
+    ///
+    /// A game with no samples says nothing about the bag, so it has no power.
+    pub fn power(&self, bag: &CubeSet) -> Result<u64> {
+        if self.samples.is_empty() {
+            anyhow::bail!("game {} draws no cubes", self.id);
+        }
+        let min = self.minimal_bag();
+        bag.counts
+            .keys()
+            .try_fold(1u64, |power, color| {
+                power.checked_mul(u64::from(min.get(color)))
             })
-            .expect("empty game");
-        return min.blue * min.red * min.green;
+            .ok_or_else(|| anyhow::anyhow!("power of game {} overflows u64", self.id))
+    }
+
+    /// The smallest bag that could have produced every sample.
+    pub fn minimal_bag(&self) -> CubeSet {
+        self.samples
+            .iter()
+            .fold(CubeSet::default(), |acc, next| acc.union(next))
     }
 
-    pub fn consistent_with(&self, other: CubeSet) -> bool {
-        self.samples.iter().all(move |s| s.consistent_with(other))
+    pub fn consistent_with(&self, bag: &CubeSet) -> bool {
+        self.samples.iter().all(|s| s.consistent_with(bag))
     }
 }
 
 impl FromStr for Game {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self> {
-        // Lines look like
-        //
-        // Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-        // Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-        let Some((game, game_desc)) = s.split_once(":") else {
-            anyhow::bail!("Invalid game description: {s}");
-        };
+        let (id, raw_samples) = utils::parse::finalize_verbose(s, parser::game(s))?;
 
-        let game_re = Regex::new("Game ([0-9]+)")?;
-        let game_id: u32 = match game_re.captures(game) {
-            Some(cap) => cap.get(1).unwrap().as_str().parse::<u32>()?,
-            None => {
-                anyhow::bail!("Invalid game: {game}");
+        let mut samples = Vec::with_capacity(raw_samples.len());
+        for (i, cubes) in raw_samples.into_iter().enumerate() {
+            let mut seen = BTreeSet::new();
+            let mut set = CubeSet::default();
+            for (count, color) in cubes {
+                // Each color should appear at most once.
+                if !seen.insert(color) {
+                    anyhow::bail!("color {color:?} appears twice in sample {}", i + 1);
+                }
+                set.set(color, count);
             }
-        };
+            samples.push(set);
+        }
 
-        let dice_re = Regex::new("([0-9]+) (red|green|blue)")?;
-        let samples: Result<Vec<CubeSet>> = game_desc
-            .split("; ")
-            .map(|sample| {
-                let (mut red, mut blue, mut green) = (None, None, None);
-                for die in sample.split(",") {
-                    let Some(c) = dice_re.captures(die) else {
-                        anyhow::bail!("Invalid dice sample: {die:?}")
-                    };
-                    let count = c.get(1).unwrap().as_str().parse::<u32>()?;
-                    let target = match c.get(2).unwrap().as_str() {
-                        "red" => &mut red,
-                        "green" => &mut green,
-                        "blue" => &mut blue,
-                        &_ => panic!("wut"),
-                    };
-
-                    // Each color should appear at most once.
-                    if target.is_some() {
-                        anyhow::bail!("Invalid dice sample: {die:?}");
-                    }
-                    *target = Some(count);
-                }
+        Ok(Game { id, samples })
+    }
+}
 
-                Ok(CubeSet {
-                    red: red.unwrap_or(0),
-                    green: green.unwrap_or(0),
-                    blue: blue.unwrap_or(0),
-                })
-            })
-            .collect();
+mod parser {
+    use nom::bytes::complete::tag;
+    use nom::character::complete::{alpha1, char};
+    use nom::combinator::{cut, eof};
+    use nom::error::context;
+    use nom::multi::separated_list1;
+    use nom::sequence::{pair, preceded, separated_pair, terminated};
+    use utils::parse::{num_verbose, VerboseResult};
+
+    /// A separator like `, `: once the punctuation is seen, exactly one
+    /// space must follow.
+    fn separator<'a>(
+        c: char,
+        expected: &'static str,
+    ) -> impl FnMut(&'a str) -> VerboseResult<'a, (char, char)> {
+        pair(char(c), cut(context(expected, char(' '))))
+    }
+
+    fn cube(input: &str) -> VerboseResult<'_, (u32, &str)> {
+        separated_pair(
+            context("a cube count like `3 blue`", num_verbose),
+            context("a single space before the color", char(' ')),
+            context("a color", alpha1),
+        )(input)
+    }
 
-        Ok(Game { id: game_id, samples: samples? })
+    /// The `(count, color)` pairs drawn in one sample.
+    pub type Cubes<'a> = Vec<(u32, &'a str)>;
+
+    fn sample(input: &str) -> VerboseResult<'_, Cubes<'_>> {
+        separated_list1(separator(',', "a single space after `,`"), cut(cube))(input)
+    }
+
+    /// A whole line, like `Game 1: 3 blue, 4 red; 1 red, 2 green`, as the
+    /// game id and the cubes in each sample. Colors aren't checked for
+    /// duplicates here.
+    pub fn game(input: &str) -> VerboseResult<'_, (u32, Vec<Cubes<'_>>)> {
+        let id = preceded(
+            context("`Game `", tag("Game ")),
+            context("a game id", num_verbose),
+        );
+        let samples = preceded(
+            pair(
+                context("`:` after the game id", char(':')),
+                cut(context("a space after `:`", char(' '))),
+            ),
+            separated_list1(separator(';', "a single space after `;`"), cut(sample)),
+        );
+        terminated(
+            pair(id, samples),
+            context("`,`, `;` or the end of the line", eof),
+        )(input)
     }
 }
 
@@ -131,7 +272,39 @@ struct Input {
     games: Vec<Game>,
 }
 
+/// Print the minimal bag for `games` and the most likely bags a little
+/// larger than it.
+fn infer(games: &[Game]) {
+    let min = inference::minimal_bag(games);
+    println!("minimal bag: {min}");
+    println!("most likely bags:");
+    for (bag, ll) in inference::rank(games, inference::candidates(&min, 5))
+        .iter()
+        .take(10)
+    {
+        println!("  {bag}  log likelihood {ll:.2}");
+    }
+}
+
 fn main() -> Result<()> {
+    if let Some(bag) = utils::flag_value("--simulate") {
+        let bag: CubeSet = bag.parse()?;
+        let mut rng = inference::Rng::new(2023);
+        infer(&inference::simulate(&bag, 100, 3, 10, &mut rng));
+        return Ok(());
+    }
+    if utils::has_flag("--infer") {
+        infer(&utils::load::<Problem2>()?.games);
+        return Ok(());
+    }
+    if let Some(bag) = utils::flag_value("--bag") {
+        let bag: CubeSet = bag.parse()?;
+        let input = utils::load::<Problem2>()?;
+        println!("Part 1: {}", possible_id_sum(&input.games, &bag));
+        println!("Part 2: {}", power_sum(&input.games, &bag)?);
+        return Ok(());
+    }
+
     utils::run_part1::<Problem2>()?;
     utils::run_part2::<Problem2>()
 }
@@ -142,8 +315,8 @@ mod test {
 
     #[test]
     fn test_p1_example() -> Result<()> {
-        let s = r#"""Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue"
+        let s = r#"Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
 Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red"#;
         let parsed = Problem2::parse(s)?;
         let expected = Input {
@@ -151,25 +324,25 @@ Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red"#;
                 Game {
                     id: 1,
                     samples: vec![
-                        CubeSet { blue: 3, red: 4, green: 0 },
-                        CubeSet { red: 1, green: 2, blue: 6 },
-                        CubeSet { red: 0, green: 2, blue: 0 },
+                        CubeSet::from([("blue", 3), ("red", 4), ("green", 0)]),
+                        CubeSet::from([("red", 1), ("green", 2), ("blue", 6)]),
+                        CubeSet::from([("red", 0), ("green", 2), ("blue", 0)]),
                     ],
                 },
                 Game {
                     id: 2,
                     samples: vec![
-                        CubeSet { blue: 1, green: 2, red: 0 },
-                        CubeSet { green: 3, blue: 4, red: 1 },
-                        CubeSet { green: 1, blue: 1, red: 0 },
+                        CubeSet::from([("blue", 1), ("green", 2), ("red", 0)]),
+                        CubeSet::from([("green", 3), ("blue", 4), ("red", 1)]),
+                        CubeSet::from([("green", 1), ("blue", 1), ("red", 0)]),
                     ],
                 },
                 Game {
                     id: 3,
                     samples: vec![
-                        CubeSet { green: 8, blue: 6, red: 20 },
-                        CubeSet { blue: 5, red: 4, green: 13 },
-                        CubeSet { green: 5, blue: 0, red: 1 },
+                        CubeSet::from([("green", 8), ("blue", 6), ("red", 20)]),
+                        CubeSet::from([("blue", 5), ("red", 4), ("green", 13)]),
+                        CubeSet::from([("green", 5), ("blue", 0), ("red", 1)]),
                     ],
                 },
             ],
@@ -182,4 +355,120 @@ Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red"#;
 
         Ok(())
     }
+
+    #[test]
+    fn test_any_colors() -> Result<()> {
+        let s = "Game 1: 3 blue, 4 yellow; 1 red\nGame 2: 9 yellow, 1 blue\nGame 3: 2 red";
+        let parsed = Problem2::parse(s)?;
+
+        let bag: CubeSet = "red=12,green=13,blue=14,yellow=5".parse()?;
+        assert_eq!(possible_id_sum(&parsed.games, &bag), 4);
+        assert_eq!(possible_id_sum(&parsed.games, &CubeSet::default_bag()), 3);
+
+        let powers = |bag: &CubeSet| -> Result<Vec<u64>> {
+            parsed.games.iter().map(|g| g.power(bag)).collect()
+        };
+        assert_eq!(powers(&"red=1,blue=1,yellow=1".parse()?)?, [12, 0, 0]);
+        assert_eq!(powers(&"red=1,yellow=1".parse()?)?, [4, 0, 0]);
+        assert_eq!(powers(&bag)?, [0, 0, 0]);
+        assert_eq!(powers(&CubeSet::default_bag())?, [0, 0, 0]);
+
+        let err = "red=12,red=3".parse::<CubeSet>().unwrap_err();
+        assert_eq!(err.to_string(), "Color \"red\" appears twice in bag");
+        let err = "red=0".parse::<CubeSet>().unwrap_err();
+        assert_eq!(err.to_string(), "Bag \"red=0\" has no cubes");
+        let err = "red=0,red=3".parse::<CubeSet>().unwrap_err();
+        assert_eq!(err.to_string(), "Color \"red\" appears twice in bag");
+        assert!("red:12".parse::<CubeSet>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |line: &str| format!("{:#}", Problem2::parse(line).unwrap_err());
+
+        assert_eq!(
+            error("Game 1: 3 blue, 2 red, 1 blue"),
+            "line 1: color \"blue\" appears twice in sample 1"
+        );
+        assert_eq!(
+            error("Game 1: 3  blue"),
+            "line 1: column 11: expected a color, found ' '"
+        );
+        assert_eq!(
+            error("Game 1: 3 blue;2 red"),
+            "line 1: column 16: expected a single space after `;`, found '2'"
+        );
+        assert_eq!(
+            error("Game 1: 3 blue; ; 2 red"),
+            "line 1: column 17: expected a cube count like `3 blue`, found ';'"
+        );
+        assert_eq!(
+            error("Game 1: 3 blue "),
+            "line 1: column 15: expected `,`, `;` or the end of the line, found ' '"
+        );
+        assert_eq!(
+            error("Game 7:"),
+            "line 1: column 8: expected a space after `:`, found end of input"
+        );
+        assert_eq!(
+            error("Game 7:1 red"),
+            "line 1: column 8: expected a space after `:`, found '1'"
+        );
+        assert_eq!(
+            error("Game 7 1 red"),
+            "line 1: column 7: expected `:` after the game id, found ' '"
+        );
+        assert_eq!(
+            error("Game 7: "),
+            "line 1: column 9: expected a cube count like `3 blue`, found end of input"
+        );
+        assert_eq!(
+            error("Game : 1 red"),
+            "line 1: column 6: expected a game id, found ':'"
+        );
+    }
+
+    #[test]
+    fn test_power_overflow() -> Result<()> {
+        let input = Problem2::parse("Game 1: 70000 red, 70000 green, 1 blue")?;
+        assert_eq!(Problem2::run2(input)?, "4900000000");
+
+        let bag = "red=1,green=1,blue=1,yellow=1".parse()?;
+        let input = Problem2::parse("Game 1: 70000 red, 70000 green, 70000 blue, 70000 yellow")?;
+        let err = power_sum(&input.games, &bag).unwrap_err();
+        assert_eq!(err.to_string(), "power of game 1 overflows u64");
+
+        let game = "Game 1: 4294967295 red, 4294967295 green, 1 blue";
+        let input = Problem2::parse(&format!("{game}\n{game}"))?;
+        let err = Problem2::run2(input).unwrap_err();
+        assert_eq!(err.to_string(), "total power overflows u64");
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_game() {
+        let input = Input {
+            games: vec![Game { id: 4, samples: vec![] }],
+        };
+        let err = input.games[0].power(&CubeSet::default_bag()).unwrap_err();
+        assert_eq!(err.to_string(), "game 4 draws no cubes");
+        let err = Problem2::run2(input).unwrap_err();
+        assert_eq!(err.to_string(), "game 4 draws no cubes");
+    }
+
+    #[test]
+    fn test_missing_colors() -> Result<()> {
+        // Green is never drawn, so no green cubes are needed.
+        let input = Problem2::parse("Game 1: 3 blue, 4 red; 1 red, 6 blue")?;
+        assert_eq!(Problem2::run2(input)?, "0");
+
+        let input = Problem2::parse("Game 5: 0 red")?;
+        assert_eq!(input.games[0].samples, [CubeSet::default()]);
+        assert_eq!(Problem2::run2(input)?, "0");
+
+        let input = Problem2::parse("Game 1: 1 red, 2 green, 3 blue\nGame 2: 0 blue")?;
+        assert_eq!(Problem2::run2(input)?, "6");
+        Ok(())
+    }
 }
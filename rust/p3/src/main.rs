@@ -1,5 +1,11 @@
-use std::str::FromStr;
+mod render;
 
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use anyhow::Context;
+use render::{Style, Window};
+use utils::grid::Grid;
 use utils::{Part1, Part2, Result};
 
 struct Problem3 {}
@@ -8,14 +14,19 @@ impl Part1 for Problem3 {
     const N: u8 = 3;
     type Input = EngineDiagram;
 
+    /// Columns count `char`s, so multi-byte symbols take one cell each, and
+    /// every row must be the same number of cells wide.
     fn parse(data: &str) -> Result<Self::Input> {
-        let lines: Result<Vec<_>> = data.lines().map(|s| s.parse::<Line>()).collect();
-        Ok(EngineDiagram { lines: lines? })
+        let grid = Grid::parse(data, Ok).context("ragged engine schematic")?;
+        EngineDiagram::new(grid)
     }
 
     fn run1(input: EngineDiagram) -> Result<String> {
         let parts = input.get_parts();
-        let total: u32 = parts.iter().map(|num| num.n).sum();
+        let total = parts
+            .iter()
+            .try_fold(0u128, |total, num| total.checked_add(num.n.into()))
+            .context("sum of part numbers overflows u128")?;
         Ok(total.to_string())
     }
 }
@@ -23,134 +34,162 @@ impl Part1 for Problem3 {
 impl Part2 for Problem3 {
     fn run2(data: Self::Input) -> Result<String> {
         let gears = data.get_gears();
-        let result: u32 = gears.iter().map(Gear::ratio).sum();
+        let result = gears
+            .iter()
+            .try_fold(0u128, |total, gear| total.checked_add(gear.ratio()))
+            .context("sum of gear ratios overflows u128")?;
         Ok(result.to_string())
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 struct EngineDiagram {
-    lines: Vec<Line>,
+    grid: Grid<char>,
+    symbols: Vec<Sym>,
+    numbers: Vec<Num>,
+    /// What occupies each cell, so adjacency queries only look at the cells
+    /// around their target rather than scanning every number or symbol.
+    index: Grid<Occupant>,
+}
+
+/// An index into `EngineDiagram::numbers` or `EngineDiagram::symbols`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Occupant {
+    Empty,
+    Number(usize),
+    Symbol(usize),
 }
 
 impl EngineDiagram {
-    pub fn iter_adjacent_lines(
-        &self,
-    ) -> impl Iterator<Item = (Option<&Line>, &Line, Option<&Line>)> {
-        self.lines.iter().enumerate().map(|(i, line)| {
-            let prev = self.lines.get(i.wrapping_sub(1));
-            let next = self.lines.get(i + 1);
-            (prev, line, next)
-        })
+    pub fn new(grid: Grid<char>) -> Result<EngineDiagram> {
+        let numbers: Vec<Num> = grid
+            .runs(char::is_ascii_digit)
+            .into_iter()
+            .map(|run| {
+                let digits: String = run.positions().map(|pos| grid[pos]).collect();
+                let n = digits.parse().with_context(|| {
+                    format!(
+                        "number {digits} at row {}, columns {}..={} does not fit in u64",
+                        run.row,
+                        run.cols.start,
+                        run.cols.end - 1
+                    )
+                })?;
+                Ok(Num {
+                    row: run.row,
+                    start: run.cols.start,
+                    end: run.cols.end - 1,
+                    n,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let symbols: Vec<Sym> = grid
+            .iter()
+            .filter(|(_, &c)| is_symbol(c))
+            .map(|((row, col), &c)| Sym { row, col, c })
+            .collect();
+
+        let mut index = grid.map(|_| Occupant::Empty);
+        for (i, num) in numbers.iter().enumerate() {
+            for col in num.start..=num.end {
+                index[(num.row, col)] = Occupant::Number(i);
+            }
+        }
+        for (i, sym) in symbols.iter().enumerate() {
+            index[(sym.row, sym.col)] = Occupant::Symbol(i);
+        }
+
+        Ok(EngineDiagram { grid, symbols, numbers, index })
     }
 
+    /// Numbers next to at least one symbol.
     pub fn get_parts(&self) -> Vec<&Num> {
-        self.iter_adjacent_lines()
-            .flat_map(|(prev, cur, next)| cur.parts(prev, next))
+        self.numbers
+            .iter()
+            .filter(|num| !self.symbols_adjacent_to(num).is_empty())
             .collect()
     }
 
+    /// `*` symbols next to exactly two numbers. Other symbols never count,
+    /// however many numbers they touch.
     pub fn get_gears(&self) -> Vec<Gear<'_>> {
-        self.iter_adjacent_lines()
-            .flat_map(|(prev, cur, next)| cur.gears(prev, next))
+        self.symbols
+            .iter()
+            .filter(|sym| sym.c == GEAR)
+            .filter_map(|sym| {
+                let [num1, num2] = self.numbers_adjacent_to(sym)[..] else {
+                    return None;
+                };
+                Some(Gear { sym, num1, num2 })
+            })
             .collect()
     }
-}
 
-#[derive(Debug, PartialEq, Eq)]
-struct Line {
-    symbols: Vec<Sym>,
-    numbers: Vec<Num>,
-}
+    /// Every number touching `sym`, diagonals included, in reading order.
+    pub fn numbers_adjacent_to(&self, sym: &Sym) -> Vec<&Num> {
+        let mut found: Vec<usize> = self
+            .index
+            .neighbors8((sym.row, sym.col))
+            .filter_map(|pos| match self.index[pos] {
+                Occupant::Number(i) => Some(i),
+                _ => None,
+            })
+            .collect();
+        // A number can touch the symbol in up to three cells.
+        found.dedup();
+        found.sort_unstable();
+        found.dedup();
+        found.into_iter().map(|i| &self.numbers[i]).collect()
+    }
 
-impl Line {
-    pub fn parts<'a>(
-        &'a self,
-        prev: Option<&'a Line>,
-        next: Option<&'a Line>,
-    ) -> impl Iterator<Item = &'a Num> {
-        let neighbors = [prev, Some(self), next];
-        self.numbers.iter().filter(move |num| {
-            neighbors
-                .iter()
-                .filter_map(|&x| x)
-                .flat_map(|line| line.symbols.iter())
-                .any(|sym| sym.adjacent_to(num))
-        })
+    /// Every symbol touching `num`, diagonals included, in reading order.
+    pub fn symbols_adjacent_to(&self, num: &Num) -> Vec<&Sym> {
+        let rows = num.row.saturating_sub(1)..=num.row + 1;
+        rows.flat_map(|row| (num.start.saturating_sub(1)..=num.end + 1).map(move |col| (row, col)))
+            .filter_map(|pos| match self.index.get(pos) {
+                Some(&Occupant::Symbol(i)) => Some(&self.symbols[i]),
+                _ => None,
+            })
+            .collect()
     }
 
-    pub fn gears<'a>(
-        &'a self,
-        prev: Option<&'a Line>,
-        next: Option<&'a Line>,
-    ) -> impl Iterator<Item = Gear<'a>> + 'a {
-        let neighbors = [prev, Some(self), next];
-        self.symbols.iter().filter_map(move |sym| {
-            let adjacent_nums = neighbors
-                .iter()
-                .filter_map(|&x| x)
-                .flat_map(|line| line.numbers.iter().filter(|num| sym.adjacent_to(num)));
-
-            let [Some(num1), Some(num2), None] = utils::take_fixed(adjacent_nums) else {
-                return None;
-            };
-
-            Some(Gear { sym, num1, num2 })
-        })
+    /// Symbols touching exactly `k` numbers.
+    pub fn symbols_with_neighbors(&self, k: usize) -> Vec<&Sym> {
+        self.symbols
+            .iter()
+            .filter(|sym| self.numbers_adjacent_to(sym).len() == k)
+            .collect()
     }
-}
 
-impl FromStr for Line {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self> {
-        let mut symbols = vec![];
-        let mut numbers = vec![];
-
-        let mut state: Option<Num> = None;
-
-        for (i, c) in s.chars().enumerate() {
-            state = match c {
-                '0'..='9' => {
-                    let digit = (c as u8 - '0' as u8) as u32;
-                    Some(match state {
-                        Some(num) => Num {
-                            n: 10 * num.n + digit,
-                            start: num.start,
-                            end: i,
-                        },
-                        None => Num { n: digit, start: i, end: i },
-                    })
-                }
-                _ => {
-                    if let Some(num) = state {
-                        numbers.push(num);
-                    }
-                    if c != '.' {
-                        symbols.push(Sym { pos: i, c });
-                    }
-                    None
-                }
-            };
-        }
+    /// Numbers touching no symbol at all.
+    pub fn isolated_numbers(&self) -> Vec<&Num> {
+        self.numbers
+            .iter()
+            .filter(|num| self.symbols_adjacent_to(num).is_empty())
+            .collect()
+    }
+}
 
-        // finalize last number
-        if let Some(num) = state {
-            numbers.push(num);
-        }
+/// The only symbol that can be a gear.
+const GEAR: char = '*';
 
-        Ok(Line { symbols, numbers })
-    }
+/// Anything other than a digit or `.` is a symbol.
+fn is_symbol(c: char) -> bool {
+    !c.is_ascii_digit() && c != '.'
 }
 
+/// A symbol at `(row, col)`.
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Sym {
-    pos: usize,
+    row: usize,
+    col: usize,
     c: char,
 }
 
-impl Sym {
-    pub fn adjacent_to(&self, num: &Num) -> bool {
-        (num.start <= self.pos + 1) && (self.pos <= num.end + 1)
+impl Display for Sym {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at ({}, {})", self.c, self.row, self.col)
     }
 }
 
@@ -162,16 +201,102 @@ struct Gear<'a> {
 }
 
 impl Gear<'_> {
-    pub fn ratio(&self) -> u32 {
-        self.num1.n * self.num2.n
+    /// The product of the two numbers, which always fits: each is a `u64`.
+    pub fn ratio(&self) -> u128 {
+        u128::from(self.num1.n) * u128::from(self.num2.n)
     }
 }
 
+/// A number spanning columns `start..=end` of `row`.
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Num {
+    row: usize,
     start: usize,
     end: usize,
-    n: u32,
+    n: u64,
+}
+
+impl Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at ({}, {}..={})",
+            self.n, self.row, self.start, self.end
+        )
+    }
+}
+
+/// List the numbers that aren't parts, and how many numbers each symbol
+/// touches.
+fn report(diagram: &EngineDiagram) {
+    let isolated = diagram.isolated_numbers();
+    println!("{} numbers touch no symbol:", isolated.len());
+    for num in isolated {
+        println!("  {num}");
+    }
+
+    let most = diagram
+        .symbols
+        .iter()
+        .map(|sym| diagram.numbers_adjacent_to(sym).len())
+        .max()
+        .unwrap_or(0);
+    for k in 0..=most {
+        let symbols = diagram.symbols_with_neighbors(k);
+        println!("{} symbols touch {k} numbers:", symbols.len());
+        for sym in symbols {
+            println!("  {sym}");
+        }
+    }
+}
+
+/// Read a window bound like `10..20`, `10..` or `..20` from a flag.
+fn range_flag(name: &str) -> Result<Range<usize>> {
+    let Some(value) = utils::flag_value(name) else {
+        return Ok(0..usize::MAX);
+    };
+    let bad = || anyhow::anyhow!("{name} wants a range like 10..20, got {value:?}");
+    let (start, end) = value.split_once("..").ok_or_else(bad)?;
+    let start = if start.is_empty() {
+        0
+    } else {
+        start.parse().map_err(|_| bad())?
+    };
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        end.parse().map_err(|_| bad())?
+    };
+    Ok(start..end)
+}
+
+fn main() -> Result<()> {
+    if utils::has_flag("--render") {
+        let style = if utils::has_flag("--plain") {
+            Style::Plain
+        } else if utils::has_flag("--color") {
+            Style::Color
+        } else {
+            Style::detect()
+        };
+        let window = Window {
+            rows: range_flag("--rows")?,
+            cols: range_flag("--cols")?,
+        };
+        print!(
+            "{}",
+            render::render(&utils::load::<Problem3>()?, style, &window)
+        );
+        return Ok(());
+    }
+    if utils::has_flag("--report") {
+        report(&utils::load::<Problem3>()?);
+        return Ok(());
+    }
+
+    utils::run_part1::<Problem3>()?;
+    utils::run_part2::<Problem3>()?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -188,50 +313,28 @@ mod test {
 617*......"#;
 
         let parsed = Problem3::parse(input.trim())?;
-        let expected = EngineDiagram {
-            lines: vec![
-                Line {
-                    symbols: vec![],
-                    numbers: vec![
-                        Num { start: 0, end: 2, n: 467 },
-                        Num { start: 5, end: 7, n: 114 },
-                    ],
-                },
-                Line {
-                    symbols: vec![Sym { pos: 3, c: '*' }],
-                    numbers: vec![],
-                },
-                Line {
-                    symbols: vec![],
-                    numbers: vec![
-                        Num { start: 2, end: 3, n: 35 },
-                        Num { start: 6, end: 8, n: 633 },
-                    ],
-                },
-                Line {
-                    symbols: vec![Sym { pos: 6, c: '#' }],
-                    numbers: vec![],
-                },
-                Line {
-                    symbols: vec![Sym { pos: 3, c: '*' }],
-                    numbers: vec![Num { start: 0, end: 2, n: 617 }],
-                },
-            ],
-        };
-
-        for (i, (l1, l2)) in parsed.lines.iter().zip(expected.lines.iter()).enumerate() {
-            assert_eq!(l1, l2, "line {i}");
-        }
-        assert_eq!(parsed, expected);
+        assert_eq!(
+            parsed.symbols,
+            [
+                Sym { row: 1, col: 3, c: '*' },
+                Sym { row: 3, col: 6, c: '#' },
+                Sym { row: 4, col: 3, c: '*' },
+            ]
+        );
+        assert_eq!(
+            parsed.numbers,
+            [
+                Num { row: 0, start: 0, end: 2, n: 467 },
+                Num { row: 0, start: 5, end: 7, n: 114 },
+                Num { row: 2, start: 2, end: 3, n: 35 },
+                Num { row: 2, start: 6, end: 8, n: 633 },
+                Num { row: 4, start: 0, end: 2, n: 617 },
+            ]
+        );
 
         let parts = parsed.get_parts();
-        let expected = vec![
-            &Num { start: 0, end: 2, n: 467 },
-            &Num { start: 2, end: 3, n: 35 },
-            &Num { start: 6, end: 8, n: 633 },
-            &Num { start: 0, end: 2, n: 617 },
-        ];
-        assert_eq!(parts, expected);
+        let parts: Vec<u64> = parts.iter().map(|num| num.n).collect();
+        assert_eq!(parts, [467, 35, 633, 617]);
 
         Ok(())
     }
@@ -279,10 +382,139 @@ mod test {
 
         Ok(())
     }
-}
 
-fn main() -> Result<()> {
-    utils::run_part1::<Problem3>()?;
-    utils::run_part2::<Problem3>()?;
-    Ok(())
+    #[test]
+    fn test_queries() -> Result<()> {
+        let input = "\
+12#34.
+......
+5*6..7
+...$..";
+        let parsed = Problem3::parse(input)?;
+
+        // Only `*` can be a gear, even though `#` also touches two numbers.
+        let gears: Vec<(char, u128)> = parsed
+            .get_gears()
+            .iter()
+            .map(|g| (g.sym.c, g.ratio()))
+            .collect();
+        assert_eq!(gears, [('*', 30)]);
+
+        let hash = &parsed.symbols[0];
+        let near_hash: Vec<String> = parsed
+            .numbers_adjacent_to(hash)
+            .iter()
+            .map(|num| num.to_string())
+            .collect();
+        assert_eq!(near_hash, ["12 at (0, 0..=1)", "34 at (0, 3..=4)"]);
+
+        let six = &parsed.numbers[3];
+        let near_six: Vec<String> = parsed
+            .symbols_adjacent_to(six)
+            .iter()
+            .map(|sym| sym.to_string())
+            .collect();
+        assert_eq!(near_six, ["* at (2, 1)", "$ at (3, 3)"]);
+
+        assert_eq!(parsed.symbols_with_neighbors(1), [&parsed.symbols[2]]);
+        let isolated: Vec<u64> = parsed.isolated_numbers().iter().map(|n| n.n).collect();
+        assert_eq!(isolated, [7]);
+
+        Ok(())
+    }
+
+    /// Pseudo-random schematics, dense enough in digits and symbols to
+    /// exercise every kind of adjacency.
+    fn generate(seed: u64, height: usize, width: usize) -> String {
+        const CELLS: &[u8] = b"............0123456789*#$+";
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| CELLS[next() % CELLS.len()] as char)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_index_matches_scan() -> Result<()> {
+        let adjacent = |sym: &Sym, num: &Num| {
+            sym.row.abs_diff(num.row) <= 1 && num.start <= sym.col + 1 && sym.col <= num.end + 1
+        };
+
+        for seed in 0..20 {
+            let diagram = Problem3::parse(&generate(seed, 12, 30))?;
+            for sym in diagram.symbols.iter() {
+                let scanned: Vec<&Num> = diagram
+                    .numbers
+                    .iter()
+                    .filter(|num| adjacent(sym, num))
+                    .collect();
+                assert_eq!(diagram.numbers_adjacent_to(sym), scanned, "{sym}");
+            }
+            for num in diagram.numbers.iter() {
+                let scanned: Vec<&Sym> = diagram
+                    .symbols
+                    .iter()
+                    .filter(|sym| adjacent(sym, num))
+                    .collect();
+                assert_eq!(diagram.symbols_adjacent_to(num), scanned, "{num}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wide_input() -> Result<()> {
+        // Box-drawing characters are multi-byte symbols that take one column
+        // each, so the `*` sits in column 3 under the 1 of
+        // `18446744073709551615`.
+        let input = "\
+╔══18446744073709551615
+║..*.
+╚══2";
+        let err = Problem3::parse(input).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "ragged engine schematic: row 1 is 5 cells wide, but row 0 is 23"
+        );
+
+        let input = "\
+╔══18446744073709551615
+║..*...................
+╚══2...................";
+        let parsed = Problem3::parse(input)?;
+        let row1: Vec<&Sym> = parsed.symbols.iter().filter(|s| s.row == 1).collect();
+        assert_eq!(
+            row1,
+            [
+                &Sym { row: 1, col: 0, c: '║' },
+                &Sym { row: 1, col: 3, c: '*' }
+            ]
+        );
+        assert_eq!(parsed.symbols.len(), 8);
+        assert_eq!(
+            Problem3::run1(Problem3::parse(input)?)?,
+            "18446744073709551617"
+        );
+        assert_eq!(Problem3::run2(parsed)?, "36893488147419103230");
+
+        // A number touching only the border is still a part number.
+        assert_eq!(Problem3::run1(Problem3::parse("║12.\n║...")?)?, "12");
+
+        let err = Problem3::parse("18446744073709551616*").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "number 18446744073709551616 at row 0, columns 0..=19 does not fit in u64"
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,170 @@
+use std::io::IsTerminal;
+use std::ops::Range;
+
+use utils::grid::Grid;
+
+use crate::EngineDiagram;
+
+/// What a cell of the schematic turned out to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Blank,
+    /// A digit of a number next to a symbol.
+    Part,
+    /// A digit of a number next to no symbol.
+    Loose,
+    Gear,
+    Symbol,
+}
+
+impl Kind {
+    fn color(self) -> &'static str {
+        match self {
+            Kind::Blank => "\x1b[2m",
+            Kind::Part => "\x1b[32m",
+            Kind::Loose => "\x1b[31m",
+            Kind::Gear => "\x1b[1;33m",
+            Kind::Symbol => "\x1b[36m",
+        }
+    }
+
+    /// The marker drawn under a cell in plain mode.
+    fn marker(self) -> char {
+        match self {
+            Kind::Blank => ' ',
+            Kind::Part => 'p',
+            Kind::Loose => 'x',
+            Kind::Gear => 'G',
+            Kind::Symbol => 's',
+        }
+    }
+}
+
+/// How to show what each cell is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    /// ANSI colors: green parts, red loose numbers, yellow gears, cyan
+    /// symbols.
+    Color,
+    /// Each row followed by a row of markers: `p` for parts, `x` for loose
+    /// numbers, `G` for gears and `s` for other symbols.
+    Plain,
+}
+
+impl Style {
+    /// Color when writing to a terminal, markers otherwise.
+    pub fn detect() -> Style {
+        if std::io::stdout().is_terminal() {
+            Style::Color
+        } else {
+            Style::Plain
+        }
+    }
+}
+
+/// The part of the schematic to draw. Ranges are clamped to the grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Window {
+    pub rows: Range<usize>,
+    pub cols: Range<usize>,
+}
+
+fn classify(diagram: &EngineDiagram) -> Grid<Kind> {
+    let mut kinds = diagram.grid.map(|_| Kind::Blank);
+    for num in diagram.numbers.iter() {
+        let kind = if diagram.symbols_adjacent_to(num).is_empty() {
+            Kind::Loose
+        } else {
+            Kind::Part
+        };
+        for col in num.start..=num.end {
+            kinds[(num.row, col)] = kind;
+        }
+    }
+    for sym in diagram.symbols.iter() {
+        kinds[(sym.row, sym.col)] = Kind::Symbol;
+    }
+    for gear in diagram.get_gears() {
+        kinds[(gear.sym.row, gear.sym.col)] = Kind::Gear;
+    }
+    kinds
+}
+
+/// Draw the schematic, marking every cell by what it turned out to be.
+pub(crate) fn render(diagram: &EngineDiagram, style: Style, window: &Window) -> String {
+    let kinds = classify(diagram);
+    let grid = &diagram.grid;
+    let rows = window.rows.start.min(grid.height())..window.rows.end.min(grid.height());
+    let cols = window.cols.start.min(grid.width())..window.cols.end.min(grid.width());
+
+    let mut out = String::new();
+    for row in rows {
+        let cells = cols
+            .clone()
+            .map(|col| (grid[(row, col)], kinds[(row, col)]));
+        match style {
+            Style::Color => {
+                let mut current = None;
+                for (c, kind) in cells {
+                    if current != Some(kind) {
+                        out.push_str(kind.color());
+                        current = Some(kind);
+                    }
+                    out.push(c);
+                }
+                out.push_str("\x1b[0m\n");
+            }
+            Style::Plain => {
+                let (text, markers): (String, String) =
+                    cells.map(|(c, kind)| (c, kind.marker())).unzip();
+                out.push_str(&text);
+                out.push('\n');
+                out.push_str(markers.trim_end());
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Problem3;
+    use utils::{Part1, Result};
+
+    #[test]
+    fn test_render_plain() -> Result<()> {
+        let input = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......";
+        let diagram = Problem3::parse(input)?;
+
+        let out = render(
+            &diagram,
+            Style::Plain,
+            &Window { rows: 0..usize::MAX, cols: 0..usize::MAX },
+        );
+        assert_eq!(
+            out.lines().step_by(2).collect::<Vec<_>>(),
+            input.lines().collect::<Vec<_>>()
+        );
+        let markers: Vec<&str> = out.lines().skip(1).step_by(2).collect();
+        assert_eq!(
+            markers,
+            ["ppp  xxx", "   G", "  pp  ppp", "      s", "ppps"]
+        );
+
+        let window = Window { rows: 1..3, cols: 2..5 };
+        let out = render(&diagram, Style::Plain, &window);
+        assert_eq!(out, ".*.\n G\n35.\npp\n");
+
+        let out = render(&diagram, Style::Color, &window);
+        assert!(out.starts_with("\x1b[2m.\x1b[1;33m*\x1b[2m.\x1b[0m\n"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, bail};
+use utils::Result;
+
+use crate::Card;
+
+/// What to do when a card wins copies of cards past the end of the table.
+/// The puzzle promises this never happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PastEnd {
+    /// Only hand out copies of the cards that exist.
+    Clamp,
+    Error,
+}
+
+/// How many copies of each card end up in hand, originals included.
+///
+/// Each copy of card `i` with `m` matches wins one more copy of each of the
+/// next `m` cards. Counts are checked, since they can grow exponentially
+/// with the number of cards.
+pub(crate) fn cascade<const W: usize>(cards: &[Card<W>], past_end: PastEnd) -> Result<Vec<u64>> {
+    let mut copies: Vec<u64> = vec![1; cards.len()];
+    for (i, card) in cards.iter().enumerate() {
+        let wins = card.match_count() as usize;
+        let last = cards.len() - 1;
+        if i + wins > last && past_end == PastEnd::Error {
+            bail!(
+                "card {} wins copies of cards {}..={}, but the last card is {}",
+                card.id,
+                card.id + 1,
+                card.id as usize + wins,
+                cards[last].id
+            );
+        }
+
+        for j in i + 1..=(i + wins).min(last) {
+            copies[j] = copies[j]
+                .checked_add(copies[i])
+                .ok_or_else(|| anyhow!("copies of card {} overflow u64", cards[j].id))?;
+        }
+    }
+    Ok(copies)
+}
+
+/// The total number of cards in hand.
+pub(crate) fn total(copies: &[u64]) -> Result<u64> {
+    copies
+        .iter()
+        .try_fold(0u64, |total, &n| total.checked_add(n))
+        .ok_or_else(|| anyhow!("total number of cards overflows u64"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Cards;
+
+    #[test]
+    fn test_cascade() -> Result<()> {
+        // Card 2 wins two copies, but only card 3 is left.
+        let cards: Cards = "Card 1: 1 | 1\nCard 2: 1 2 | 1 2\nCard 3: 1 | 2".parse()?;
+
+        let err = cascade(&cards.cards, PastEnd::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "card 2 wins copies of cards 3..=4, but the last card is 3"
+        );
+
+        let copies = cascade(&cards.cards, PastEnd::Clamp)?;
+        assert_eq!(copies, [1, 2, 3]);
+        assert_eq!(total(&copies)?, 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow() -> Result<()> {
+        // Every card wins a copy of all the rest, so card n has 2^(n-1)
+        // copies.
+        let lines: Vec<String> = (1..=66)
+            .map(|id| {
+                let nums: Vec<String> = (0..66 - id).map(|n| n.to_string()).collect();
+                format!("Card {id}: {} | {}", nums.join(" "), nums.join(" "))
+            })
+            .collect();
+        let cards: Cards = lines[..64].join("\n").parse()?;
+        let copies = cascade(&cards.cards, PastEnd::Clamp)?;
+        assert_eq!(copies[63], 1 << 63);
+        assert_eq!(total(&copies)?, u64::MAX);
+
+        let cards: Cards = lines.join("\n").parse()?;
+        let err = cascade(&cards.cards, PastEnd::Error).unwrap_err();
+        assert_eq!(err.to_string(), "copies of card 65 overflow u64");
+        Ok(())
+    }
+}
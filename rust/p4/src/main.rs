@@ -1,4 +1,10 @@
-use anyhow::bail;
+mod cascade;
+mod numbers;
+mod trace;
+
+use anyhow::{anyhow, bail};
+use cascade::PastEnd;
+use numbers::NumberSet;
 use std::str::FromStr;
 
 use utils::{Part1, Part2, Result};
@@ -10,81 +16,155 @@ impl Part1 for Problem4 {
     type Input = Cards;
 
     fn parse(data: &str) -> Result<Self::Input> {
-        let cards: Vec<Card> = data.lines().map(Card::from_str).collect::<Result<_>>()?;
-        Ok(Cards { cards })
+        data.parse()
     }
 
     fn run1(cards: Cards) -> Result<String> {
-        let result: u32 = cards.cards.iter().map(Card::point_value).sum();
-        Ok(result.to_string())
+        let total = cards.cards.iter().try_fold(0u64, |total, card| {
+            total
+                .checked_add(card.point_value()?)
+                .ok_or_else(|| anyhow!("total points overflow u64"))
+        })?;
+        Ok(total.to_string())
     }
 }
 
 impl Part2 for Problem4 {
     fn run2(cards: Cards) -> Result<String> {
-        let mut copies: Vec<u32> = vec![1; cards.cards.len()];
-        for (i, card) in cards.cards.iter().enumerate() {
-            for j in 0..card.match_count() {
-                copies[i + j as usize + 1] += copies[i];
-            }
-        }
-
-        let result: u32 = copies.iter().sum();
-        Ok(result.to_string())
+        let copies = cascade::cascade(&cards.cards, PastEnd::Error)?;
+        Ok(cascade::total(&copies)?.to_string())
     }
 }
 
-struct Cards {
-    cards: Vec<Card>,
+#[derive(Debug)]
+struct Cards<const W: usize = 2> {
+    cards: Vec<Card<W>>,
+}
+
+impl<const W: usize> FromStr for Cards<W> {
+    type Err = anyhow::Error;
+
+    /// Parse every card, checking that the ids run 1, 2, 3, ... with no
+    /// gaps or repeats.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut cards: Vec<Card<W>> = vec![];
+        for line in s.lines() {
+            let card: Card<W> = line.parse()?;
+            let expected = cards.len() as u32 + 1;
+            if card.id == 0 {
+                bail!("card ids start at 1, found card 0");
+            }
+            if card.id < expected {
+                bail!("card {} appears more than once", card.id);
+            }
+            if card.id > expected {
+                bail!("expected card {expected}, found card {}", card.id);
+            }
+            cards.push(card);
+        }
+        Ok(Cards { cards })
+    }
 }
 
-struct Card {
-    winners: Vec<u8>,
-    nums: Vec<u8>,
+/// A scratchcard. Numbers must be below `128 * W`.
+#[derive(Debug)]
+struct Card<const W: usize = 2> {
+    id: u32,
+    winners: NumberSet<W>,
+    nums: NumberSet<W>,
 }
 
-impl Card {
+impl<const W: usize> Card<W> {
     pub fn match_count(&self) -> u32 {
-        self.nums
-            .iter()
-            .filter(|n| self.winners.contains(n))
-            .count() as u32
+        self.winners.common(&self.nums)
     }
 
-    pub fn point_value(&self) -> u32 {
-        let match_count = self.match_count();
-        if match_count == 0 {
-            0
-        } else {
-            2u32.pow(match_count - 1)
+    /// One point for the first match, doubled for each match after it.
+    /// With enough numbers per card this can outgrow any integer, so it's
+    /// checked.
+    pub fn point_value(&self) -> Result<u64> {
+        match self.match_count() {
+            0 => Ok(0),
+            n => 1u64
+                .checked_shl(n - 1)
+                .ok_or_else(|| anyhow!("points for card {} overflow u64", self.id)),
         }
     }
 }
 
-impl FromStr for Card {
+impl<const W: usize> FromStr for Card<W> {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self> {
-        let Some((_, nums)) = s.split_once(": ") else {
+        let Some((label, nums)) = s.split_once(':') else {
             bail!("invalid card {s:?}");
         };
-        let Some((winners, nums)) = nums.split_once(" | ") else {
-            bail!("invalid card {s:?}");
+        let id = match label
+            .strip_prefix("Card")
+            .map(|id| id.trim().parse::<u32>())
+        {
+            Some(Ok(id)) => id,
+            _ => bail!("invalid card label {label:?}"),
+        };
+        let Some((winners, nums)) = nums.split_once('|') else {
+            bail!("card {id} has no `|` between its winning numbers and its numbers");
         };
 
-        let winners = winners
-            .trim()
-            .split_ascii_whitespace()
-            .map(u8::from_str)
-            .collect::<std::result::Result<Vec<u8>, _>>()?;
+        let parse_set = |list: &str, name: &str| {
+            let mut set = NumberSet::<W>::default();
+            for n in list.split_ascii_whitespace() {
+                let value: u32 = n
+                    .parse()
+                    .map_err(|e| anyhow!("card {id}: invalid number {n:?} in {name}: {e}"))?;
+                if value >= NumberSet::<W>::LIMIT {
+                    bail!(
+                        "card {id}: {name} include {value}, outside 0..{}",
+                        NumberSet::<W>::LIMIT
+                    );
+                }
+                if !set.insert(value) {
+                    bail!("card {id}: {name} list {value} twice");
+                }
+            }
+            Ok::<_, anyhow::Error>(set)
+        };
 
-        let nums = nums
-            .trim()
-            .split_ascii_whitespace()
-            .map(u8::from_str)
-            .collect::<std::result::Result<Vec<u8>, _>>()?;
+        Ok(Card {
+            id,
+            winners: parse_set(winners, "winning numbers")?,
+            nums: parse_set(nums, "numbers")?,
+        })
+    }
+}
 
-        Ok(Card { winners, nums })
+fn main() -> Result<()> {
+    let past_end = if utils::has_flag("--clamp") {
+        PastEnd::Clamp
+    } else {
+        PastEnd::Error
+    };
+
+    if utils::has_flag("--trace") || utils::has_flag("--trace-dot") {
+        let steps = trace::trace(&utils::load::<Problem4>()?.cards, past_end)?;
+        if utils::has_flag("--trace-dot") {
+            print!("{}", trace::dot(&steps));
+        } else {
+            print!("{}", trace::table(&steps));
+        }
+        return Ok(());
+    }
+    if utils::has_flag("--copies") {
+        let cards = utils::load::<Problem4>()?;
+        let copies = cascade::cascade(&cards.cards, past_end)?;
+        for (card, n) in cards.cards.iter().zip(copies.iter()) {
+            println!("card {}: {n}", card.id);
+        }
+        println!("total: {}", cascade::total(&copies)?);
+        return Ok(());
     }
+
+    utils::run_part1::<Problem4>()?;
+    utils::run_part2::<Problem4>()?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -107,10 +187,56 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"#
 
         Ok(())
     }
-}
 
-fn main() -> Result<()> {
-    utils::run_part1::<Problem4>()?;
-    utils::run_part2::<Problem4>()?;
-    Ok(())
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| Problem4::parse(input).unwrap_err().to_string();
+
+        assert_eq!(
+            error("Card 1: 1 2 | 3\nCard 3: 1 | 2"),
+            "expected card 2, found card 3"
+        );
+        assert_eq!(
+            error("Card 1: 1 2 | 3\nCard 1: 1 | 2"),
+            "card 1 appears more than once"
+        );
+        assert_eq!(error("Card 0: 1 | 2"), "card ids start at 1, found card 0");
+        assert_eq!(
+            error("Card 1: 1 | 2\nCard 0: 1 | 2"),
+            "card ids start at 1, found card 0"
+        );
+        assert_eq!(
+            error("Card 1: 1 300 | 3"),
+            "card 1: winning numbers include 300, outside 0..256"
+        );
+        assert_eq!(error("Card 1: 1 2 | 3 3"), "card 1: numbers list 3 twice");
+        assert_eq!(
+            error("Card 1: 1 2 3"),
+            "card 1 has no `|` between its winning numbers and its numbers"
+        );
+
+        let wide: Cards<4> = "Card 1: 300 500 | 500 1 300".parse().unwrap();
+        assert_eq!(wide.cards[0].match_count(), 2);
+    }
+
+    #[test]
+    fn test_point_overflow() -> Result<()> {
+        let card = |id: u32, matches: u32| {
+            let nums: Vec<String> = (0..matches).map(|n| n.to_string()).collect();
+            format!("Card {id}: {} | {}", nums.join(" "), nums.join(" "))
+        };
+
+        let cards = Problem4::parse(&card(1, 64))?;
+        assert_eq!(cards.cards[0].point_value()?, 1 << 63);
+        assert_eq!(Problem4::run1(cards)?, (1u64 << 63).to_string());
+
+        let cards = Problem4::parse(&card(1, 65))?;
+        let err = Problem4::run1(cards).unwrap_err();
+        assert_eq!(err.to_string(), "points for card 1 overflow u64");
+
+        let input = format!("{}\n{}", card(1, 64), card(2, 64));
+        let err = Problem4::run1(Problem4::parse(&input)?).unwrap_err();
+        assert_eq!(err.to_string(), "total points overflow u64");
+        Ok(())
+    }
 }
@@ -0,0 +1,58 @@
+/// A set of the numbers `0..128 * W`, one bit each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NumberSet<const W: usize> {
+    words: [u128; W],
+}
+
+impl<const W: usize> Default for NumberSet<W> {
+    fn default() -> Self {
+        NumberSet { words: [0; W] }
+    }
+}
+
+impl<const W: usize> NumberSet<W> {
+    /// One past the largest number the set can hold.
+    pub const LIMIT: u32 = 128 * W as u32;
+
+    /// Add `n`, returning whether it was newly added. Panics if `n` is out
+    /// of range.
+    pub fn insert(&mut self, n: u32) -> bool {
+        assert!(n < Self::LIMIT, "{n} is outside 0..{}", Self::LIMIT);
+        let bit = 1 << (n % 128);
+        let word = &mut self.words[n as usize / 128];
+        let added = *word & bit == 0;
+        *word |= bit;
+        added
+    }
+
+    /// How many numbers are in both sets.
+    pub fn common(&self, other: &NumberSet<W>) -> u32 {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_number_set() {
+        let mut a = NumberSet::<2>::default();
+        let mut b = NumberSet::<2>::default();
+        for n in [0, 5, 127, 128, 255] {
+            assert!(a.insert(n));
+        }
+        assert!(!a.insert(5));
+        for n in [5, 128, 200] {
+            b.insert(n);
+        }
+
+        assert_eq!(NumberSet::<2>::LIMIT, 256);
+        assert_eq!(a.common(&a), 5);
+        assert_eq!(a.common(&b), 2);
+    }
+}
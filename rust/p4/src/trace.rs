@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+use utils::Result;
+
+use crate::cascade::{self, PastEnd};
+use crate::Card;
+
+/// What one card did during the cascade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Step {
+    pub card: u32,
+    /// Copies in hand once every earlier card has been scratched.
+    pub copies: u64,
+    pub matches: u32,
+    /// The ids of the cards that each copy of this one wins a copy of.
+    pub adds_to: Option<RangeInclusive<u32>>,
+}
+
+/// Run the cascade, recording each card's part in it.
+///
+/// A card's copy count is final by the time it's scratched, since only
+/// earlier cards add to it, so every later card it wins gets exactly that
+/// many more copies.
+pub(crate) fn trace<const W: usize>(cards: &[Card<W>], past_end: PastEnd) -> Result<Vec<Step>> {
+    let copies = cascade::cascade(cards, past_end)?;
+    let last = cards.last().map_or(0, |card| card.id);
+    Ok(cards
+        .iter()
+        .zip(copies)
+        .map(|(card, copies)| {
+            let matches = card.match_count();
+            let end = (card.id + matches).min(last);
+            Step {
+                card: card.id,
+                copies,
+                matches,
+                adds_to: (end > card.id).then(|| card.id + 1..=end),
+            }
+        })
+        .collect())
+}
+
+/// The trace as a table, one row per card.
+pub(crate) fn table(steps: &[Step]) -> String {
+    let mut out = format!("{:>6} {:>12} {:>8}  adds to\n", "card", "copies", "matches");
+    for step in steps {
+        let adds_to = match &step.adds_to {
+            Some(ids) if ids.start() == ids.end() => ids.start().to_string(),
+            Some(ids) => format!("{}..={}", ids.start(), ids.end()),
+            None => "-".to_string(),
+        };
+        writeln!(
+            out,
+            "{:>6} {:>12} {:>8}  {adds_to}",
+            step.card, step.copies, step.matches
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// The trace as a Graphviz digraph: a node per card labelled with its
+/// copies, and an edge from each card to every card it adds to, labelled
+/// with how many copies flow along it.
+pub(crate) fn dot(steps: &[Step]) -> String {
+    let mut out = String::from("digraph cascade {\n");
+    for step in steps {
+        writeln!(
+            out,
+            "  {} [label=\"card {}\\n{} copies\"];",
+            step.card, step.card, step.copies
+        )
+        .unwrap();
+    }
+    for step in steps {
+        for to in step.adds_to.clone().into_iter().flatten() {
+            writeln!(out, "  {} -> {to} [label=\"{}\"];", step.card, step.copies).unwrap();
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Part1, Problem4};
+
+    #[test]
+    fn test_trace() -> Result<()> {
+        let input = "\
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+        let cards = Problem4::parse(input)?;
+        let steps = trace(&cards.cards, PastEnd::Error)?;
+
+        let expected = "  card       copies  matches  adds to
+     1            1        4  2..=5
+     2            2        2  3..=4
+     3            4        2  4..=5
+     4            8        1  5
+     5           14        0  -
+     6            1        0  -
+";
+        assert_eq!(table(&steps), expected);
+
+        let dot = dot(&steps);
+        assert!(dot.contains("  3 -> 5 [label=\"4\"];"));
+        assert_eq!(dot.matches("->").count(), 9);
+
+        Ok(())
+    }
+}
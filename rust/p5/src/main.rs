@@ -46,7 +46,7 @@ impl Part2 for Problem5 {
                 (start..start + len)
                     .into_par_iter()
                     .map(|s| input.seed_location(s))
-                    .reduce(|| std::u64::MAX, std::cmp::min)
+                    .reduce(|| u64::MAX, std::cmp::min)
             })
             .reduce(std::cmp::min)
             .unwrap();
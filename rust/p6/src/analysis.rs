@@ -0,0 +1,223 @@
+use std::fmt::{self, Display};
+
+use anyhow::anyhow;
+use num::integer::Roots;
+use num::PrimInt;
+use utils::Result;
+
+use crate::Race;
+
+/// Everything worth knowing about how to play a single race.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Analysis<T> {
+    pub race: Race<T>,
+    /// The charge times that reach `max_distance`: one if the duration is
+    /// even, two adjacent times if it's odd.
+    pub best_charge: Vec<T>,
+    pub max_distance: T,
+    pub winning: Option<Winning<T>>,
+}
+
+/// The range of charge times that beat the record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Winning<T> {
+    pub lo: T,
+    pub hi: T,
+    pub count: T,
+    /// How far past the record the shortest winning charge goes.
+    pub lo_margin: T,
+    /// How far past the record the longest winning charge goes.
+    pub hi_margin: T,
+}
+
+impl<T: PrimInt + Roots + Display> Race<T> {
+    pub fn analyze(&self) -> Result<Analysis<T>> {
+        let max_distance = self.max_distance()?;
+        let (h, e) = self.halves();
+        let best_charge = if e.is_zero() { vec![h] } else { vec![h, h + e] };
+
+        let winning = self.winning_interval()?.map(|(lo, hi)| {
+            // Both ends are inside the winning interval, so their distances
+            // are at most the peak and can't overflow.
+            let margin = |t| self.distance_for_charge(t).unwrap() - self.record;
+            Winning {
+                lo,
+                hi,
+                count: hi - lo + T::one(),
+                lo_margin: margin(lo),
+                hi_margin: margin(hi),
+            }
+        });
+
+        Ok(Analysis {
+            race: *self,
+            best_charge,
+            max_distance,
+            winning,
+        })
+    }
+
+    /// The distance covered for every charge time from zero to the full
+    /// duration, or `None` where it doesn't fit in `T`.
+    pub fn curve(&self) -> impl Iterator<Item = (T, Option<T>)> + '_ {
+        num::range_inclusive(T::zero(), self.duration).map(|t| (t, self.distance_for_charge(t)))
+    }
+
+    /// Build a race of the given duration with exactly `ways` winning charge
+    /// times, by setting the record to the distance of the charge time just
+    /// outside the winning window.
+    ///
+    /// Winning windows are symmetric about `duration / 2`, so `ways` must be
+    /// odd for even durations and even for odd ones. Handy for building test
+    /// inputs with a known answer.
+    pub fn with_ways(duration: T, ways: T) -> Option<Race<T>> {
+        let (one, two) = (T::one(), T::one() + T::one());
+        if ways.is_zero() || duration.is_zero() || ways >= duration {
+            return None;
+        }
+        let race = Race { duration, record: T::zero() };
+        let (h, e) = race.halves();
+        if (ways % two) == e {
+            return None;
+        }
+
+        let k = (ways - e - one) / two;
+        let record = race.distance_for_charge(h - k - one)?;
+        Some(Race { duration, record })
+    }
+}
+
+impl<T: Display> Display for Analysis<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Race { duration, record } = &self.race;
+        writeln!(f, "Race: {duration}ms, record {record}mm")?;
+
+        let best: Vec<String> = self.best_charge.iter().map(|t| format!("{t}ms")).collect();
+        writeln!(
+            f,
+            "  best charge: {} ({}mm)",
+            best.join(" or "),
+            self.max_distance
+        )?;
+
+        match &self.winning {
+            Some(Winning { lo, hi, count, lo_margin, hi_margin }) => {
+                writeln!(f, "  winning charge: {lo}ms..={hi}ms ({count} ways)")?;
+                writeln!(
+                    f,
+                    "  margin: +{lo_margin}mm at {lo}ms, +{hi_margin}mm at {hi}ms"
+                )?;
+            }
+            None => writeln!(f, "  winning charge: none")?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Render a race's distance curve as a table, marking the charge times that
+/// beat the record.
+pub(crate) fn curve_table<T: PrimInt + Roots + Display>(race: &Race<T>) -> String {
+    let mut out = format!("{:>8} {:>10}\n", "charge", "distance");
+    for (t, d) in race.curve() {
+        match d {
+            Some(d) => {
+                let marker = if d > race.record { " *" } else { "" };
+                out.push_str(&format!("{t:>8} {d:>10}{marker}\n"));
+            }
+            None => out.push_str(&format!("{t:>8} {:>10}\n", "overflow")),
+        }
+    }
+    out
+}
+
+/// Build puzzle input for races given as `duration:ways` pairs, like
+/// `7:4,15:8,30:9`, each with a record that exactly `ways` charge times
+/// beat.
+pub(crate) fn generate(spec: &str) -> Result<String> {
+    let mut races: Vec<Race> = vec![];
+    for item in spec.split(',').map(str::trim) {
+        let bad = || anyhow!("--generate wants duration:ways pairs, got {item:?}");
+        let (duration, ways) = item.split_once(':').ok_or_else(bad)?;
+        let duration = duration.parse().map_err(|_| bad())?;
+        let ways = ways.parse().map_err(|_| bad())?;
+        let race = Race::with_ways(duration, ways).ok_or_else(|| {
+            anyhow!("no {duration}ms race has exactly {ways} winning charge times")
+        })?;
+        races.push(race);
+    }
+
+    let row = |label: &str, values: Vec<u64>| {
+        let values: Vec<String> = values.iter().map(u64::to_string).collect();
+        format!("{label:<9} {}", values.join(" "))
+    };
+    Ok(format!(
+        "{}\n{}\n",
+        row("Time:", races.iter().map(|r| r.duration).collect()),
+        row("Distance:", races.iter().map(|r| r.record).collect())
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let analysis = Race { duration: 7u64, record: 9 }.analyze()?;
+        assert_eq!(analysis.best_charge, vec![3, 4]);
+        assert_eq!(analysis.max_distance, 12);
+        assert_eq!(
+            analysis.winning,
+            Some(Winning {
+                lo: 2,
+                hi: 5,
+                count: 4,
+                lo_margin: 1,
+                hi_margin: 1
+            })
+        );
+
+        let analysis = Race { duration: 30u64, record: 225 }.analyze()?;
+        assert_eq!(analysis.best_charge, vec![15]);
+        assert_eq!(analysis.winning, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_ways() -> Result<()> {
+        for duration in 1..40u64 {
+            for ways in 0..duration + 2 {
+                match Race::with_ways(duration, ways) {
+                    Some(race) => assert_eq!(race.record_count()?, ways, "{race:?}"),
+                    None => assert!(ways == 0 || ways >= duration || ways % 2 == duration % 2),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate() -> Result<()> {
+        use crate::{Part1, Problem6};
+
+        let input = generate("7:4, 15:8, 30:9")?;
+        assert_eq!(input, "Time:     7 15 30\nDistance: 6 36 200\n");
+        assert_eq!(Problem6::run1(Problem6::parse(&input)?)?, "288");
+
+        let err = generate("7:3").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no 7ms race has exactly 3 winning charge times"
+        );
+        assert!(generate("7").is_err());
+
+        // 8 * 32 = 256 is past u8.
+        let table = curve_table(&Race { duration: 40u8, record: 200 });
+        assert!(table.contains("       7        231 *\n"));
+        assert!(table.contains("       8   overflow\n"));
+
+        Ok(())
+    }
+}
@@ -1,3 +1,11 @@
+mod analysis;
+
+use std::fmt::Display;
+
+use anyhow::Context;
+use num::integer::Roots;
+use num::PrimInt;
+use parser::Kerning;
 use utils::{Part1, Part2, Result};
 
 struct Problem6;
@@ -7,102 +15,304 @@ impl Part1 for Problem6 {
     type Input = Input;
 
     fn parse(data: &str) -> Result<Self::Input> {
-        utils::parse::finalize(parser::parse_input(data.trim()))
+        let races = parser::parse_input(data.trim(), Kerning::Separate)?;
+        // Only part 2 reads the row this way, so a joined number that
+        // doesn't fit mustn't stop part 1.
+        let joined = parser::parse_input(data.trim(), Kerning::Ignore).map(|races| {
+            let [joined] = races[..] else {
+                unreachable!("ignoring kerning always yields one race");
+            };
+            joined
+        });
+        Ok(Input { races, joined })
     }
 
     fn run1(input: Self::Input) -> Result<String> {
-        let counts = input.races.iter().map(Race::record_count);
-        let prod = counts.fold(1, |a, b| a * b);
+        let counts = input
+            .races
+            .iter()
+            .map(Race::record_count)
+            .collect::<Result<Vec<_>>>()?;
+        let prod: u64 = counts.into_iter().product();
         Ok(prod.to_string())
     }
 }
 
 impl Part2 for Problem6 {
     fn run2(input: Self::Input) -> anyhow::Result<String> {
-        let race = input
-            .races
-            .iter()
-            .copied()
-            .reduce(|a, b| a.concat_fields(b))
-            .expect("should be multiple races");
-
-        Ok(race.record_count().to_string())
+        Ok(input.joined?.record_count()?.to_string())
     }
 }
 
 #[derive(Debug)]
 struct Input {
     races: Vec<Race>,
+    /// The single race read with the spaces between columns ignored, or
+    /// why it couldn't be read.
+    joined: Result<Race>,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Race {
-    duration: u64,
-    record: u64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Race<T = u64> {
+    duration: T,
+    record: T,
 }
 
-impl Race {
-    fn concat_fields(self, other: Race) -> Race {
-        let duration = format!(
-            "{}{}",
-            self.duration.to_string(),
-            other.duration.to_string()
-        )
-        .parse::<u64>()
-        .unwrap();
+impl<T: PrimInt + Roots + Display> Race<T> {
+    /// Count the charge times that beat the record.
+    fn record_count(&self) -> Result<T> {
+        Ok(match self.winning_interval()? {
+            Some((lo, hi)) => hi - lo + T::one(),
+            None => T::zero(),
+        })
+    }
+
+    /// The first and last charge times that beat the record, if any.
+    ///
+    /// Charging for `t` covers `t * (d - t)`, a parabola symmetric about `d /
+    /// 2`. Writing `d = 2h + e` and `t = h - k`, the distance is `h(h + e) -
+    /// k(k + e)`, so the winning charge times are exactly those with `k(k + e)
+    /// < h(h + e) - record`. The largest such `k` is within one of an integer
+    /// square root, so no floating point is involved.
+    ///
+    /// Fails if the peak distance `h(h + e)` doesn't fit in `T`.
+    fn winning_interval(&self) -> Result<Option<(T, T)>> {
+        let one = T::one();
+        let (h, e) = self.halves();
+
+        let peak = self.max_distance()?;
+        if peak <= self.record {
+            return Ok(None);
+        }
 
-        let record = format!("{}{}", self.record.to_string(), other.record.to_string())
-            .parse::<u64>()
-            .unwrap();
+        // Largest k with k(k + e) <= n.
+        let n = peak - self.record - one;
+        let s = n.sqrt();
+        let k = match s.checked_mul(&(s + e)) {
+            Some(p) if p <= n => s,
+            _ => s - one,
+        };
 
-        Race { duration, record }
+        Ok(Some((h - k, h + e + k)))
     }
 
-    fn record_count(&self) -> u64 {
-        (1..self.duration)
-            .map(|d| self.distance_for_charge(d))
-            .filter(|&t| t > self.record)
-            .count() as u64
+    /// The furthest any charge time can go in this race.
+    fn max_distance(&self) -> Result<T> {
+        let (h, e) = self.halves();
+        h.checked_mul(&(h + e))
+            .with_context(|| format!("peak distance overflows for duration {}", self.duration))
+    }
+
+    /// Split the duration into `h` and `e` with `duration = 2h + e`.
+    fn halves(&self) -> (T, T) {
+        let two = T::one() + T::one();
+        (self.duration / two, self.duration % two)
     }
 
-    fn distance_for_charge(&self, charge_time: u64) -> u64 {
-        let race_time = self.duration.saturating_sub(charge_time);
-        race_time * charge_time
+    fn distance_for_charge(&self, charge_time: T) -> Option<T> {
+        let race_time = self.duration.checked_sub(&charge_time)?;
+        race_time.checked_mul(&charge_time)
     }
 }
 
 mod parser {
-    use super::{Input, Race};
+    use super::Race;
+    use anyhow::{anyhow, ensure};
     use nom::bytes::complete::tag;
-    use nom::character::complete::multispace1;
-    use nom::combinator::{all_consuming, verify};
+    use nom::character::complete::{digit1, space0, space1};
+    use nom::combinator::all_consuming;
+    use nom::multi::separated_list1;
     use nom::sequence::{preceded, separated_pair, tuple};
     use nom::{IResult, Parser};
-    use utils::parse::whitespace_delimited_nums;
-
-    pub(crate) fn parse_input(input: &str) -> IResult<&str, Input> {
-        let nums = whitespace_delimited_nums::<u64>;
-        let times = preceded(tuple((tag("Time:"), multispace1)), nums);
-        let dists = preceded(tuple((tag("Distance:"), multispace1)), nums);
-        let parser = all_consuming(separated_pair(times, tag("\n"), dists));
-        let parser = verify(parser, |(times, dists)| times.len() == dists.len());
-
-        parser
-            .map(|(times, dists)| {
-                let races = times
-                    .iter()
-                    .copied()
-                    .zip(dists)
-                    .map(|(duration, record)| Race { duration, record })
-                    .collect::<Vec<_>>();
-                Input { races }
+    use num::PrimInt;
+
+    /// How to read the whitespace between the digit columns of a row.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum Kerning {
+        /// Each column is its own race.
+        Separate,
+        /// The spaces are bad kerning: all columns form a single race.
+        Ignore,
+    }
+
+    fn row<'a>(label: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<&'a str>> {
+        preceded(tuple((tag(label), space0)), separated_list1(space1, digit1))
+    }
+
+    fn rows(input: &str) -> IResult<&str, (Vec<&str>, Vec<&str>)> {
+        all_consuming(separated_pair(row("Time:"), tag("\n"), row("Distance:"))).parse(input)
+    }
+
+    /// Read a run of digit columns as one number, failing on overflow
+    /// rather than wrapping.
+    fn read_columns<T: PrimInt>(columns: &[&str]) -> Option<T> {
+        let ten = T::from(10)?;
+        columns
+            .iter()
+            .flat_map(|col| col.bytes())
+            .try_fold(T::zero(), |n, b| {
+                n.checked_mul(&ten)?.checked_add(&T::from(b - b'0')?)
             })
-            .parse(input)
     }
+
+    fn group<'a, 'b>(columns: &'b [&'a str], kerning: Kerning) -> Vec<&'b [&'a str]> {
+        match kerning {
+            Kerning::Separate => columns.chunks(1).collect(),
+            Kerning::Ignore => vec![columns],
+        }
+    }
+
+    pub(crate) fn parse_input<T: PrimInt>(
+        input: &str,
+        kerning: Kerning,
+    ) -> anyhow::Result<Vec<Race<T>>> {
+        let (times, dists) = utils::parse::finalize(rows(input))?;
+        ensure!(
+            times.len() == dists.len(),
+            "{} times but {} distances",
+            times.len(),
+            dists.len()
+        );
+
+        let read = |name, columns: &[&str]| {
+            read_columns::<T>(columns).ok_or_else(|| {
+                anyhow!(
+                    "{name} {} does not fit in {}",
+                    columns.concat(),
+                    std::any::type_name::<T>()
+                )
+            })
+        };
+
+        group(&times, kerning)
+            .into_iter()
+            .zip(group(&dists, kerning))
+            .map(|(time, dist)| {
+                Ok(Race {
+                    duration: read("time", time)?,
+                    record: read("distance", dist)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Print an analysis of every race, plus the kerned race from part 2.
+fn report(input: &Input) -> Result<()> {
+    for race in input.races.iter() {
+        println!("{}", race.analyze()?);
+        println!("{}", analysis::curve_table(race));
+    }
+    match &input.joined {
+        Ok(joined) => println!("Joined {}", joined.analyze()?),
+        Err(e) => println!("Joined race: {e}"),
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
+    if let Some(spec) = utils::flag_value("--generate") {
+        print!("{}", analysis::generate(&spec)?);
+        return Ok(());
+    }
+    if utils::has_flag("--report") {
+        return report(&utils::load::<Problem6>()?);
+    }
+
     utils::run_part1::<Problem6>()?;
     utils::run_part2::<Problem6>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn brute_force_count(race: Race) -> u64 {
+        (1..race.duration)
+            .map(|t| t * (race.duration - t))
+            .filter(|&d| d > race.record)
+            .count() as u64
+    }
+
+    #[test]
+    fn test_example() -> Result<()> {
+        let input = r#"
+Time:      7  15   30
+Distance:  9  40  200"#;
+        let parsed = Problem6::parse(input)?;
+        assert_eq!(Problem6::run1(parsed)?, "288");
+
+        let parsed = Problem6::parse(input)?;
+        assert_eq!(Problem6::run2(parsed)?, "71503");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kerning() -> Result<()> {
+        let input = "Time:      7  15   30\nDistance:  9  40  200";
+        let races = parser::parse_input::<u8>(input, Kerning::Separate)?;
+        assert_eq!(
+            races.iter().map(|r| r.duration).collect::<Vec<_>>(),
+            [7, 15, 30]
+        );
+
+        let races = parser::parse_input::<u64>(input, Kerning::Ignore)?;
+        assert_eq!(races.len(), 1);
+        assert_eq!((races[0].duration, races[0].record), (71530, 940200));
+
+        let err = parser::parse_input::<u16>(input, Kerning::Ignore).unwrap_err();
+        assert_eq!(err.to_string(), "time 71530 does not fit in u16");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_joined_overflow_only_fails_part2() -> Result<()> {
+        let input = format!(
+            "Time:     {}\nDistance: {}",
+            ["7 15 30"; 4].join(" "),
+            ["9 40 200"; 4].join(" ")
+        );
+        let parsed = Problem6::parse(&input)?;
+        assert_eq!(Problem6::run1(parsed)?, (288u64.pow(4)).to_string());
+
+        let err = Problem6::run2(Problem6::parse(&input)?).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "time 71530715307153071530 does not fit in u64"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_count_matches_brute_force() -> Result<()> {
+        for duration in 0..80u64 {
+            let peak = (duration / 2) * (duration - duration / 2);
+            for record in 0..=peak + 2 {
+                let race = Race { duration, record };
+                assert_eq!(race.record_count()?, brute_force_count(race), "{race:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_count_u128() -> Result<()> {
+        let small = Race { duration: 71530u64, record: 940200 };
+        let wide = Race { duration: 71530u128, record: 940200 };
+        assert_eq!(small.record_count()? as u128, wide.record_count()?);
+
+        // The peak (2^63 - 1)(2^63) fits in u128, but nowhere near u64.
+        let duration = u64::MAX as u128;
+        let race = Race { duration, record: 0 };
+        assert_eq!(race.record_count()?, duration - 1);
+
+        let race = Race { duration: u128::MAX, record: 0 };
+        assert!(race.record_count().is_err());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
 
 use enum_map::{Enum, EnumMap};
 
@@ -8,128 +10,365 @@ pub struct BidHand<T: Ord> {
     pub bid: u32,
 }
 
+/// The rules of a camel cards variant: how cards rank against each other
+/// when breaking ties, which cards are wild, and how hands are classified.
+///
+/// Only `rank` is required. Wild cards default to none, and the default
+/// `hand_type` counts every wild card toward the largest group of matching
+/// non-wild cards, which is always the best use of them. Straights and
+/// flushes are only recognized if the ruleset turns them on.
+pub trait Ruleset: Copy + Eq {
+    /// Count `N` cards of consecutive rank as a straight.
+    const STRAIGHTS: bool = false;
+    /// Count `N` cards of the same suit as a flush.
+    const FLUSHES: bool = false;
+
+    /// Strength of a single card for tie-breaking. Higher is stronger.
+    fn rank(card: Card) -> u8;
+
+    fn is_wild(_card: Card) -> bool {
+        false
+    }
+
+    fn hand_type(cards: &[Card], suits: Option<&[Suit]>) -> HandType {
+        let mut map: EnumMap<Card, u8> = EnumMap::default();
+        let mut wild = 0;
+        for &card in cards.iter() {
+            if Self::is_wild(card) {
+                wild += 1;
+            } else {
+                map[card] += 1;
+            }
+        }
+
+        let mut groups: Vec<u8> = map.values().copied().filter(|&n| n > 0).collect();
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        match groups.first_mut() {
+            Some(top) => *top += wild,
+            None => groups.push(wild),
+        }
+        let grouped = HandType { groups, pattern: None };
+
+        // Wild cards don't help complete a pattern: a straight or flush has
+        // to be there as dealt.
+        let straight = Self::STRAIGHTS && is_straight::<Self>(cards);
+        let flush = Self::FLUSHES && suits.is_some_and(is_flush);
+        let pattern = match (straight, flush) {
+            (true, true) => Some(Pattern::StraightFlush),
+            (true, false) => Some(Pattern::Straight),
+            (false, true) => Some(Pattern::Flush),
+            (false, false) => None,
+        };
+
+        match pattern {
+            Some(pattern) => {
+                let patterned = HandType {
+                    groups: vec![1; cards.len()],
+                    pattern: Some(pattern),
+                };
+                std::cmp::max(grouped, patterned)
+            }
+            None => grouped,
+        }
+    }
+}
+
+/// Whether the cards are consecutive in `R`'s rank order, with no wild
+/// cards. The strongest card can also play below the weakest card that
+/// isn't wild, as the ace does in `A2345`.
+fn is_straight<R: Ruleset>(cards: &[Card]) -> bool {
+    if cards.iter().any(|&c| R::is_wild(c)) {
+        return false;
+    }
+
+    let mut ladder = Card::ALL;
+    ladder.sort_by_key(|&c| R::rank(c));
+    let rung = |card: Card| ladder.iter().position(|&c| c == card).unwrap() as i32;
+    let consecutive = |ranks: &[i32]| ranks.windows(2).all(|w| w[1] == w[0] + 1);
+
+    let mut ranks: Vec<i32> = cards.iter().map(|&c| rung(c)).collect();
+    ranks.sort_unstable();
+    if consecutive(&ranks) {
+        return true;
+    }
+
+    let top = rung(ladder[ladder.len() - 1]);
+    let bottom = ladder
+        .iter()
+        .find(|&&c| !R::is_wild(c))
+        .map_or(0, |&c| rung(c));
+    match ranks.pop() {
+        Some(rank) if rank == top => {
+            ranks.insert(0, bottom - 1);
+            consecutive(&ranks)
+        }
+        _ => false,
+    }
+}
+
+fn is_flush(suits: &[Suit]) -> bool {
+    suits.windows(2).all(|w| w[0] == w[1])
+}
+
+/// The part 1 rules: no wild cards, aces high.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct Hand(pub [Card; 5]);
+pub struct Standard;
 
-impl PartialOrd<Hand> for Hand {
-    fn partial_cmp(&self, other: &Hand) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl Ruleset for Standard {
+    fn rank(card: Card) -> u8 {
+        card as u8
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.hand_type().cmp(&other.hand_type()) {
-            Ordering::Equal => self.0.cmp(&other.0),
-            other => other,
+/// The part 2 rules: jacks are jokers, which are wild but rank lowest.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Jokers;
+
+impl Ruleset for Jokers {
+    fn rank(card: Card) -> u8 {
+        match card {
+            Card::Jack => 0,
+            card => card as u8 + 1,
+        }
+    }
+
+    fn is_wild(card: Card) -> bool {
+        card == Card::Jack
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Hand<R = Standard, const N: usize = 5> {
+    pub cards: [Card; N],
+    /// Suits are optional, and only matter to rulesets that score flushes.
+    pub suits: Option<[Suit; N]>,
+    rules: PhantomData<R>,
+}
+
+impl<const N: usize> Hand<Standard, N> {
+    pub fn suited(cards: [Card; N], suits: [Suit; N]) -> Self {
+        Hand {
+            cards,
+            suits: Some(suits),
+            rules: PhantomData,
         }
     }
 }
 
-impl Hand {
+impl<R: Ruleset, const N: usize> Hand<R, N> {
     pub fn hand_type(&self) -> HandType {
-        let mut map: EnumMap<Card, u8> = EnumMap::default();
-        for card in self.0.iter() {
-            map[*card] += 1;
+        R::hand_type(&self.cards, self.suits.as_ref().map(|s| &s[..]))
+    }
+
+    /// The same cards, played under different rules.
+    pub fn with_rules<S: Ruleset>(self) -> Hand<S, N> {
+        Hand {
+            cards: self.cards,
+            suits: self.suits,
+            rules: PhantomData,
         }
+    }
 
-        let (mut top, mut sec) = (0, 0);
-        for &count in map.values() {
-            if count > top {
-                sec = top;
-                top = count;
-            } else if count > sec {
-                sec = count;
-            }
+    /// The best concrete hand the wild cards can turn this into.
+    ///
+    /// Wild cards all become the most common non-wild card (the strongest
+    /// one, if several are tied), or the strongest non-wild card if the
+    /// whole hand is wild. A hand that scores best on a straight or flush
+    /// plays as dealt.
+    pub fn resolve(&self) -> Resolved<N> {
+        let hand_type = self.hand_type();
+        let wild: Vec<usize> = (0..N).filter(|&i| R::is_wild(self.cards[i])).collect();
+        if wild.is_empty() || hand_type.pattern.is_some() {
+            return Resolved {
+                cards: self.cards,
+                substitution: None,
+                hand_type,
+            };
+        }
+
+        let mut counts: EnumMap<Card, u8> = EnumMap::default();
+        for &card in self.cards.iter().filter(|&&c| !R::is_wild(c)) {
+            counts[card] += 1;
+        }
+        // Prefer non-wild cards, but if every card is wild the wild cards
+        // become the strongest one.
+        let card = Card::ALL
+            .into_iter()
+            .max_by_key(|&c| (!R::is_wild(c), counts[c], R::rank(c)))
+            .expect("Card::ALL is not empty");
+
+        let mut cards = self.cards;
+        for &i in wild.iter() {
+            cards[i] = card;
+        }
+
+        Resolved {
+            cards,
+            substitution: Some(Substitution { positions: wild, card }),
+            hand_type: R::hand_type(&cards, self.suits.as_ref().map(|s| &s[..])),
         }
+    }
 
-        match (top, sec) {
-            (5, _) => HandType::FiveOfAKind,
-            (4, _) => HandType::FourOfAKind,
-            (3, 2) => HandType::FullHouse,
-            (3, 1) => HandType::ThreeOfAKind,
-            (2, 2) => HandType::TwoPair,
-            (2, 1) => HandType::Pair,
-            (1, 1) => HandType::HighCard,
-            _ => panic!("invalid counts {top}, {sec}"),
+    /// Compare two hands, saying what decided the comparison.
+    pub fn explain_cmp(&self, other: &Self) -> (Ordering, Decider) {
+        let (ours, theirs) = (self.hand_type(), other.hand_type());
+        if ours != theirs {
+            return (ours.cmp(&theirs), Decider::HandType { ours, theirs });
         }
+
+        let mismatch = self
+            .cards
+            .iter()
+            .zip(other.cards.iter())
+            .enumerate()
+            .find_map(|(i, (&a, &b))| match R::rank(a).cmp(&R::rank(b)) {
+                Ordering::Equal => None,
+                ord => Some((ord, Decider::Card { position: i, ours: a, theirs: b })),
+            });
+        mismatch.unwrap_or((Ordering::Equal, Decider::Identical))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct Hand2(pub [Card2; 5]);
+impl<R: Ruleset, const N: usize> PartialOrd for Hand<R, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-impl From<Hand> for Hand2 {
-    fn from(value: Hand) -> Self {
-        Hand2(utils::map_fixed(value.0, |&c| Card2::from(c)))
+impl<R: Ruleset, const N: usize> Ord for Hand<R, N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.explain_cmp(other).0
     }
 }
 
-impl Hand2 {
-    fn hand_type(&self) -> HandType {
-        let mut map: EnumMap<Card2, u8> = EnumMap::default();
-        for card in self.0.iter() {
-            map[*card] += 1;
+impl<R, const N: usize> fmt::Display for Hand<R, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, card) in self.cards.iter().enumerate() {
+            write!(f, "{card}")?;
+            if let Some(suits) = &self.suits {
+                write!(f, "{}", suits[i])?;
+            }
         }
+        Ok(())
+    }
+}
 
-        let mut js = 0;
-        let (mut top, mut sec) = (0, 0);
-        for (card, count) in map.into_iter() {
-            if card == Card2::Joker {
-                js = count;
-                continue;
-            }
+/// The concrete hand a hand with wild cards plays as.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Resolved<const N: usize = 5> {
+    pub cards: [Card; N],
+    /// What the wild cards became, or `None` if there weren't any.
+    pub substitution: Option<Substitution>,
+    pub hand_type: HandType,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Substitution {
+    /// Positions of the wild cards in the hand.
+    pub positions: Vec<usize>,
+    /// The card they all stand in for.
+    pub card: Card,
+}
 
-            if count > top {
-                sec = top;
-                top = count;
-            } else if count > sec {
-                sec = count;
+/// What decided a comparison between two hands.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Decider {
+    HandType {
+        ours: HandType,
+        theirs: HandType,
+    },
+    /// The hand types were equal, so the first differing card decided.
+    Card {
+        position: usize,
+        ours: Card,
+        theirs: Card,
+    },
+    Identical,
+}
+
+impl fmt::Display for Decider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decider::HandType { ours, theirs } => write!(f, "{ours} vs {theirs}"),
+            Decider::Card { position, ours, theirs } => {
+                write!(f, "card {}: {ours} vs {theirs}", position + 1)
             }
+            Decider::Identical => write!(f, "identical"),
         }
+    }
+}
+
+/// The classification of a hand of any size.
+///
+/// A hand is described by the sizes of its groups of matching cards, largest
+/// first: `[3, 2]` is a full house, `[2, 2, 2, 1]` is three pair in a seven
+/// card hand. Hands with bigger groups are stronger, which for five cards
+/// gives the usual camel cards order.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct HandType {
+    pub groups: Vec<u8>,
+    /// A straight or flush, for rulesets that score them.
+    pub pattern: Option<Pattern>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Pattern {
+    Straight,
+    Flush,
+    StraightFlush,
+}
 
-        match (top + js, sec) {
-            (5, _) => HandType::FiveOfAKind,
-            (4, _) => HandType::FourOfAKind,
-            (3, 2) => HandType::FullHouse,
-            (3, 1) => HandType::ThreeOfAKind,
-            (2, 2) => HandType::TwoPair,
-            (2, 1) => HandType::Pair,
-            (1, 1) => HandType::HighCard,
-            _ => panic!("invalid counts {top}, {sec}"),
+impl HandType {
+    /// The key hand types are ordered by.
+    ///
+    /// Patterns slot in where poker puts them: straights and flushes between
+    /// three of a kind and a full house, straight flushes between four and
+    /// five of a kind. Group sizes are doubled to leave room for them.
+    fn strength(&self) -> Vec<u16> {
+        match self.pattern {
+            Some(Pattern::Straight) => vec![6, 3],
+            Some(Pattern::Flush) => vec![6, 3, 1],
+            Some(Pattern::StraightFlush) => vec![9],
+            None => self.groups.iter().map(|&n| 2 * n as u16).collect(),
         }
     }
 }
 
-impl PartialOrd<Hand2> for Hand2 {
-    fn partial_cmp(&self, other: &Hand2) -> Option<Ordering> {
+impl PartialOrd for HandType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for Hand2 {
+impl Ord for HandType {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.hand_type().cmp(&other.hand_type()) {
-            Ordering::Equal => self.0.cmp(&other.0),
-            other => other,
-        }
+        self.strength().cmp(&other.strength())
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub enum HandType {
-    HighCard,
-    Pair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+impl fmt::Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(pattern) = self.pattern {
+            return write!(f, "{pattern:?}");
+        }
+        let pairs = self.groups.iter().filter(|&&n| n == 2).count();
+        match self.groups[..] {
+            [5, ..] => write!(f, "FiveOfAKind"),
+            [4, ..] => write!(f, "FourOfAKind"),
+            [3, 2, ..] => write!(f, "FullHouse"),
+            [3, ..] => write!(f, "ThreeOfAKind"),
+            [2, 2, 2, ..] => write!(f, "{pairs}Pair"),
+            [2, 2, ..] => write!(f, "TwoPair"),
+            [2, ..] => write!(f, "Pair"),
+            [1, ..] | [] => write!(f, "HighCard"),
+            [n, ..] => write!(f, "{n}OfAKind"),
+        }
+    }
 }
 
-impl From<[Card; 5]> for Hand {
-    fn from(cards: [Card; 5]) -> Self {
-        Hand(cards)
+impl<const N: usize> From<[Card; N]> for Hand<Standard, N> {
+    fn from(cards: [Card; N]) -> Self {
+        Hand { cards, suits: None, rules: PhantomData }
     }
 }
 
@@ -150,39 +389,294 @@ pub enum Card {
     Ace,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Enum, PartialOrd, Ord)]
-pub enum Card2 {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Queen,
-    King,
-    Ace,
+impl Card {
+    pub const ALL: [Card; 13] = [
+        Card::Two,
+        Card::Three,
+        Card::Four,
+        Card::Five,
+        Card::Six,
+        Card::Seven,
+        Card::Eight,
+        Card::Nine,
+        Card::Ten,
+        Card::Jack,
+        Card::Queen,
+        Card::King,
+        Card::Ace,
+    ];
+
+    pub fn from_label(label: char) -> Option<Card> {
+        Card::ALL.into_iter().find(|c| c.label() == label)
+    }
+
+    pub fn label(self) -> char {
+        match self {
+            Card::Two => '2',
+            Card::Three => '3',
+            Card::Four => '4',
+            Card::Five => '5',
+            Card::Six => '6',
+            Card::Seven => '7',
+            Card::Eight => '8',
+            Card::Nine => '9',
+            Card::Ten => 'T',
+            Card::Jack => 'J',
+            Card::Queen => 'Q',
+            Card::King => 'K',
+            Card::Ace => 'A',
+        }
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub fn from_label(label: char) -> Option<Suit> {
+        [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+            .into_iter()
+            .find(|s| s.label() == label)
+    }
+
+    pub fn label(self) -> char {
+        match self {
+            Suit::Clubs => 'c',
+            Suit::Diamonds => 'd',
+            Suit::Hearts => 'h',
+            Suit::Spades => 's',
+        }
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
 }
 
-impl From<Card> for Card2 {
-    fn from(value: Card) -> Self {
-        match value {
-            Card::Two => Card2::Two,
-            Card::Three => Card2::Three,
-            Card::Four => Card2::Four,
-            Card::Five => Card2::Five,
-            Card::Six => Card2::Six,
-            Card::Seven => Card2::Seven,
-            Card::Eight => Card2::Eight,
-            Card::Nine => Card2::Nine,
-            Card::Ten => Card2::Ten,
-            Card::Jack => Card2::Joker,
-            Card::Queen => Card2::Queen,
-            Card::King => Card2::King,
-            Card::Ace => Card2::Ace,
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Card::*;
+
+    /// Jacks are wild, but still rank between tens and queens.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    struct HighJacks;
+
+    impl Ruleset for HighJacks {
+        fn rank(card: Card) -> u8 {
+            Standard::rank(card)
+        }
+
+        fn is_wild(card: Card) -> bool {
+            card == Jack
+        }
+    }
+
+    /// Standard ranks, plus poker's straights and flushes.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct Poker;
+
+    impl Ruleset for Poker {
+        const STRAIGHTS: bool = true;
+        const FLUSHES: bool = true;
+
+        fn rank(card: Card) -> u8 {
+            Standard::rank(card)
+        }
+    }
+
+    /// Poker straights, with wild jacks that still rank between tens and
+    /// queens.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    struct WildPoker;
+
+    impl Ruleset for WildPoker {
+        const STRAIGHTS: bool = true;
+
+        fn rank(card: Card) -> u8 {
+            Standard::rank(card)
+        }
+
+        fn is_wild(card: Card) -> bool {
+            card == Jack
+        }
+    }
+
+    impl HandType {
+        /// A hand type with no pattern, from its group sizes.
+        pub fn of(groups: &[u8]) -> HandType {
+            HandType { groups: groups.to_vec(), pattern: None }
+        }
+    }
+
+    #[test]
+    fn test_rulesets() {
+        let jacks = Hand::from([Jack, Jack, Two, Three, Four]);
+        let threes = Hand::from([Ten, Ten, Ten, Two, Three]);
+
+        assert_eq!(jacks.hand_type(), HandType::of(&[2, 1, 1, 1]));
+        assert!(jacks < threes);
+
+        let (jacks, threes) = (jacks.with_rules::<Jokers>(), threes.with_rules::<Jokers>());
+        assert_eq!(jacks.hand_type(), HandType::of(&[3, 1, 1]));
+        assert!(jacks < threes);
+
+        let (jacks, threes) = (
+            jacks.with_rules::<HighJacks>(),
+            threes.with_rules::<HighJacks>(),
+        );
+        assert_eq!(jacks.hand_type(), HandType::of(&[3, 1, 1]));
+        assert!(jacks > threes);
+    }
+
+    #[test]
+    fn test_hand_type_order() {
+        let order = [
+            HandType::of(&[1, 1, 1, 1, 1]),
+            HandType::of(&[2, 1, 1, 1]),
+            HandType::of(&[2, 2, 1]),
+            HandType::of(&[3, 1, 1]),
+            HandType {
+                groups: vec![1; 5],
+                pattern: Some(Pattern::Straight),
+            },
+            HandType {
+                groups: vec![1; 5],
+                pattern: Some(Pattern::Flush),
+            },
+            HandType::of(&[3, 2]),
+            HandType::of(&[4, 1]),
+            HandType {
+                groups: vec![1; 5],
+                pattern: Some(Pattern::StraightFlush),
+            },
+            HandType::of(&[5]),
+        ];
+        assert!(order.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_seven_card_hands() {
+        let three_pair = Hand::from([Two, Two, Five, Five, Nine, Nine, Ace]);
+        assert_eq!(three_pair.hand_type(), HandType::of(&[2, 2, 2, 1]));
+        assert_eq!(three_pair.hand_type().to_string(), "3Pair");
+
+        let full_house = Hand::from([Two, Two, Two, Five, Five, Nine, Ace]);
+        assert!(three_pair < full_house);
+
+        let wild = Hand::from([Jack, Jack, Jack, Five, Five, Five, Five]).with_rules::<Jokers>();
+        assert_eq!(wild.hand_type(), HandType::of(&[7]));
+        assert_eq!(wild.hand_type().to_string(), "7OfAKind");
+    }
+
+    #[test]
+    fn test_poker_patterns() {
+        use Suit::*;
+
+        let straight = Hand::from([Nine, Ten, Jack, Queen, King]);
+        assert_eq!(straight.hand_type(), HandType::of(&[1, 1, 1, 1, 1]));
+        assert_eq!(
+            straight.with_rules::<Poker>().hand_type().pattern,
+            Some(Pattern::Straight)
+        );
+
+        let flush = Hand::suited([Two, Two, Five, Nine, Ace], [Hearts; 5]).with_rules::<Poker>();
+        assert_eq!(flush.hand_type().pattern, Some(Pattern::Flush));
+        assert!(flush > Hand::from([Ace, Ace, Ace, Two, Three]).with_rules::<Poker>());
+
+        // Still a full house, which beats the flush it also is.
+        let full_house = Hand::suited([Two, Two, Five, Five, Five], [Hearts; 5]);
+        assert_eq!(
+            full_house.with_rules::<Poker>().hand_type(),
+            HandType::of(&[3, 2])
+        );
+
+        let wheel = Hand::from([Ace, Two, Three, Four, Five]).with_rules::<Poker>();
+        assert_eq!(wheel.hand_type().pattern, Some(Pattern::Straight));
+        let wrapped = Hand::from([Queen, King, Ace, Two, Three]).with_rules::<Poker>();
+        assert_eq!(wrapped.hand_type().pattern, None);
+
+        let suits = [Spades, Spades, Spades, Spades, Spades];
+        let straight_flush = Hand::suited([Two, Three, Four, Five, Six], suits);
+        assert_eq!(
+            straight_flush.with_rules::<Poker>().hand_type().pattern,
+            Some(Pattern::StraightFlush)
+        );
+    }
+
+    #[test]
+    fn test_wild_straights() {
+        // The wild jack only makes a pair; it can't fill its own slot.
+        let hand = Hand::from([Nine, Ten, Jack, Queen, King]).with_rules::<WildPoker>();
+        assert_eq!(hand.hand_type(), HandType::of(&[2, 1, 1, 1]));
+
+        let hand = Hand::from([Ten, Queen, King, Ace, Nine]).with_rules::<WildPoker>();
+        assert_eq!(hand.hand_type().pattern, None);
+
+        let wheel = Hand::from([Five, Four, Three, Two, Ace]).with_rules::<WildPoker>();
+        assert_eq!(wheel.hand_type().pattern, Some(Pattern::Straight));
+        assert_eq!(wheel.resolve().cards, wheel.cards);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let hand = Hand::from([King, Ten, Jack, Jack, Ten]).with_rules::<Jokers>();
+        let resolved = hand.resolve();
+        assert_eq!(resolved.cards, [King, Ten, Ten, Ten, Ten]);
+        assert_eq!(
+            resolved.substitution,
+            Some(Substitution { positions: vec![2, 3], card: Ten })
+        );
+        assert_eq!(resolved.hand_type, HandType::of(&[4, 1]));
+
+        let hand = Hand::from([Jack; 5]).with_rules::<Jokers>();
+        assert_eq!(hand.resolve().cards, [Ace; 5]);
+
+        // The resolved hand always has the type the ruleset assigns.
+        for a in Card::ALL {
+            for b in Card::ALL {
+                let hand = Hand::from([a, Jack, b, Jack, a]).with_rules::<Jokers>();
+                assert_eq!(hand.resolve().hand_type, hand.hand_type(), "{hand}");
+            }
         }
     }
+
+    #[test]
+    fn test_explain_cmp() {
+        let a = Hand::from([King, King, Six, Seven, Seven]);
+        let b = Hand::from([King, Ten, Jack, Jack, Ten]);
+        assert_eq!(
+            a.explain_cmp(&b),
+            (
+                Ordering::Greater,
+                Decider::Card { position: 1, ours: King, theirs: Ten }
+            )
+        );
+
+        let (a, b) = (a.with_rules::<Jokers>(), b.with_rules::<Jokers>());
+        assert_eq!(
+            a.explain_cmp(&b),
+            (
+                Ordering::Less,
+                Decider::HandType {
+                    ours: HandType::of(&[2, 2, 1]),
+                    theirs: HandType::of(&[4, 1])
+                }
+            )
+        );
+        assert_eq!(a.explain_cmp(&a), (Ordering::Equal, Decider::Identical));
+    }
 }
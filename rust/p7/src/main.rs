@@ -1,6 +1,6 @@
 mod hand;
 
-use hand::{BidHand, Card, Hand, Hand2};
+use hand::{BidHand, Card, Hand, Jokers, Ruleset, Standard};
 
 use utils::{Part1, Part2};
 
@@ -11,40 +11,76 @@ impl Part1 for Problem7 {
     type Input = Input;
 
     fn parse(data: &str) -> anyhow::Result<Self::Input> {
-        utils::parse::finalize(parser::parse_input(data.trim()))
+        let hands = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| parser::parse_line(i + 1, line.trim_end()))
+            .collect::<Result<_, _>>()?;
+        Ok(Input { hands })
     }
 
     fn run1(input: Self::Input) -> anyhow::Result<String> {
-        let mut hands = input.hands;
-        hands.sort_by_key(|h| h.hand);
-
-        let total: usize = hands
-            .into_iter()
-            .enumerate()
-            .map(|(i, h)| (i + 1) * h.bid as usize)
-            .sum();
-
-        Ok(total.to_string())
+        Ok(total_winnings::<Standard>(&input.hands).to_string())
     }
 }
 
 impl Part2 for Problem7 {
     fn run2(input: Self::Input) -> anyhow::Result<String> {
-        let mut hands: Vec<_> = input
-            .hands
-            .into_iter()
-            .map(|BidHand { hand, bid }| BidHand { bid, hand: Hand2::from(hand) })
-            .collect();
-        hands.sort_by_key(|h| h.hand);
-
-        let total: usize = hands
-            .into_iter()
-            .enumerate()
-            .map(|(i, h)| (i + 1) * h.bid as usize)
-            .sum();
+        Ok(total_winnings::<Jokers>(&input.hands).to_string())
+    }
+}
+
+/// Sort the hands from weakest to strongest under the given rules.
+fn ranked<R: Ruleset>(hands: &[BidHand<Hand>]) -> Vec<BidHand<Hand<R>>> {
+    let mut hands: Vec<_> = hands
+        .iter()
+        .map(|&BidHand { hand, bid }| BidHand { bid, hand: hand.with_rules::<R>() })
+        .collect();
+    hands.sort_by_key(|h| h.hand);
+    hands
+}
+
+/// Rank the hands under the given rules and sum each bid times its rank.
+fn total_winnings<R: Ruleset>(hands: &[BidHand<Hand>]) -> usize {
+    ranked::<R>(hands)
+        .into_iter()
+        .enumerate()
+        .map(|(i, h)| (i + 1) * h.bid as usize)
+        .sum()
+}
 
-        Ok(total.to_string())
+/// Render every hand in rank order with what it plays as, its winnings, and
+/// what put it above the hand ranked just below it.
+fn ranked_table<R: Ruleset>(hands: &[BidHand<Hand>]) -> String {
+    let hands = ranked::<R>(hands);
+    let mut out = format!(
+        "{:>5} {:<5} {:<12} {:<18} {:>5} {:>9}  {}\n",
+        "rank", "hand", "type", "plays as", "bid", "winnings", "beats previous on"
+    );
+
+    for (i, BidHand { hand, bid }) in hands.iter().enumerate() {
+        let resolved = hand.resolve();
+        let plays_as = match &resolved.substitution {
+            Some(sub) => format!("{} (wild as {})", Hand::from(resolved.cards), sub.card),
+            None => "-".to_string(),
+        };
+        let decider = match i.checked_sub(1) {
+            Some(prev) => hand.explain_cmp(&hands[prev].hand).1.to_string(),
+            None => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "{:>5} {:<5} {:<12} {:<18} {:>5} {:>9}  {}\n",
+            i + 1,
+            hand.to_string(),
+            resolved.hand_type.to_string(),
+            plays_as,
+            bid,
+            (i + 1) * *bid as usize,
+            decider,
+        ));
     }
+    out
 }
 
 struct Input {
@@ -52,54 +88,182 @@ struct Input {
 }
 
 mod parser {
-    use nom::bytes::complete::tag;
-    use nom::character::complete::one_of;
-    use nom::combinator::all_consuming;
-    use nom::multi::separated_list1;
-    use nom::sequence::tuple;
-    use nom::Parser;
-    use nom::{sequence::separated_pair, IResult};
+    use std::fmt;
+
+    use nom::character::complete::{anychar, char, digit1};
+    use nom::combinator::{all_consuming, map_opt, opt};
+    use nom::multi::count;
+    use nom::sequence::{pair, separated_pair};
+    use nom::{Finish, IResult, Parser};
+
+    use super::{BidHand, Card, Hand, Standard};
+    use crate::hand::Suit;
 
-    use super::{BidHand, Card, Hand, Input};
+    /// A line of the input that couldn't be read as a hand and bid.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct ParseError {
+        /// 1-based line number.
+        pub line: usize,
+        /// The hand as written on that line.
+        pub hand: String,
+        pub reason: Reason,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Reason {
+        /// Something other than the next card, suit or bid digit, at a
+        /// 1-based column. `found` is `None` if the line ended early.
+        Unexpected { column: usize, found: Option<char> },
+        /// Some cards have suits and others don't.
+        MixedSuits,
+        /// The bid doesn't fit in a u32.
+        BidOutOfRange,
+    }
+
+    impl fmt::Display for ParseError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "line {}: invalid hand {:?}: ", self.line, self.hand)?;
+            match self.reason {
+                Reason::Unexpected { column, found: Some(c) } => {
+                    write!(f, "unexpected {c:?} at column {column}")
+                }
+                Reason::Unexpected { column, found: None } => {
+                    write!(f, "line ends early at column {column}")
+                }
+                Reason::MixedSuits => write!(f, "only some cards have suits"),
+                Reason::BidOutOfRange => write!(f, "bid is larger than {}", u32::MAX),
+            }
+        }
+    }
+
+    impl std::error::Error for ParseError {}
 
     fn card(input: &str) -> IResult<&str, Card> {
-        one_of("AKQJ23456789T")
-            .map(|c| match c {
-                'A' => Card::Ace,
-                'K' => Card::King,
-                'Q' => Card::Queen,
-                'J' => Card::Jack,
-                '2' => Card::Two,
-                '3' => Card::Three,
-                '4' => Card::Four,
-                '5' => Card::Five,
-                '6' => Card::Six,
-                '7' => Card::Seven,
-                '8' => Card::Eight,
-                '9' => Card::Nine,
-                'T' => Card::Ten,
-                _ => panic!("bad card: {c}"),
-            })
-            .parse(input)
-    }
-
-    pub fn parse_input(input: &str) -> IResult<&str, Input> {
-        let cards = tuple((card, card, card, card, card));
-        let bid = utils::parse::num::<u32>;
-        let hand = separated_pair(cards, tag(" "), bid).map(|((a, b, c, d, e), n)| BidHand {
-            hand: Hand::from([a, b, c, d, e]),
-            bid: n,
-        });
-        let parser = separated_list1(tag("\n"), hand);
-
-        all_consuming(parser)
-            .map(|hands| Input { hands })
-            .parse(input)
+        map_opt(anychar, Card::from_label).parse(input)
+    }
+
+    fn suit(input: &str) -> IResult<&str, Suit> {
+        map_opt(anychar, Suit::from_label).parse(input)
+    }
+
+    /// Parse `N` cards, each optionally followed by a suit, like `32T3K` or
+    /// `3h2hTd3sKc`.
+    fn cards<const N: usize>(input: &str) -> IResult<&str, Vec<(Card, Option<Suit>)>> {
+        count(pair(card, opt(suit)), N).parse(input)
+    }
+
+    /// Build a hand from parsed cards. Either every card has a suit or none
+    /// do.
+    fn build_hand<const N: usize>(cards: Vec<(Card, Option<Suit>)>) -> Option<Hand<Standard, N>> {
+        let (cards, suits): (Vec<Card>, Vec<Option<Suit>>) = cards.into_iter().unzip();
+        let cards: [Card; N] = cards.try_into().ok()?;
+        if suits.iter().all(Option::is_none) {
+            return Some(Hand::from(cards));
+        }
+        let suits: Option<Vec<Suit>> = suits.into_iter().collect();
+        Some(Hand::suited(cards, suits?.try_into().ok()?))
+    }
+
+    /// Parse one line of input, like `32T3K 765`, into a hand of `N` cards
+    /// and its bid.
+    pub fn parse_line<const N: usize>(
+        line: usize,
+        text: &str,
+    ) -> Result<BidHand<Hand<Standard, N>>, ParseError> {
+        let error = |reason| ParseError {
+            line,
+            hand: text
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            reason,
+        };
+
+        let mut parser = all_consuming(separated_pair(cards::<N>, char(' '), digit1));
+        let (cards, bid) = match parser.parse(text).finish() {
+            Ok((_, parsed)) => parsed,
+            Err(nom::error::Error { input, .. }) => {
+                let offset = text.len() - input.len();
+                let column = text[..offset].chars().count() + 1;
+                let found = input.chars().next();
+                return Err(error(Reason::Unexpected { column, found }));
+            }
+        };
+
+        let hand = build_hand(cards).ok_or_else(|| error(Reason::MixedSuits))?;
+        let bid = bid.parse().map_err(|_| error(Reason::BidOutOfRange))?;
+        Ok(BidHand { hand, bid })
     }
 }
 
 fn main() -> anyhow::Result<()> {
+    if utils::has_flag("--table") {
+        let input = utils::load::<Problem7>()?;
+        println!("{}", ranked_table::<Standard>(&input.hands));
+        println!("{}", ranked_table::<Jokers>(&input.hands));
+        return Ok(());
+    }
+
     utils::run_part1::<Problem7>()?;
     utils::run_part2::<Problem7>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_example() -> anyhow::Result<()> {
+        let input = r#"
+32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483"#;
+        assert_eq!(Problem7::run1(Problem7::parse(input)?)?, "6440");
+        assert_eq!(Problem7::run2(Problem7::parse(input)?)?, "5905");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_line() -> anyhow::Result<()> {
+        let hand = parser::parse_line::<7>(1, "2255993 10")?.hand;
+        assert_eq!(hand.hand_type().to_string(), "3Pair");
+
+        let hand = parser::parse_line::<5>(1, "9hThJhQhKs 10")?.hand;
+        assert_eq!(hand.to_string(), "9hThJhQhKs");
+        assert_eq!(hand.suits.map(|s| s[4]), Some(hand::Suit::Spades));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use parser::Reason;
+
+        let reason = |text| parser::parse_line::<5>(3, text).unwrap_err().reason;
+        assert_eq!(
+            reason("32X3K 765"),
+            Reason::Unexpected { column: 3, found: Some('X') }
+        );
+        assert_eq!(
+            reason("32T3 765"),
+            Reason::Unexpected { column: 5, found: Some(' ') }
+        );
+        assert_eq!(
+            reason("32T3K"),
+            Reason::Unexpected { column: 6, found: None }
+        );
+        assert_eq!(reason("3h2T3K 765"), Reason::MixedSuits);
+        assert_eq!(reason("32T3K 4294967296"), Reason::BidOutOfRange);
+
+        let input = "32T3K 765\nT55J5 684\nKK6Z7 28\nKTJJT 220";
+        let err = Problem7::parse(input).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "line 3: invalid hand \"KK6Z7\": unexpected 'Z' at column 4"
+        );
+    }
+}
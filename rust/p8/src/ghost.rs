@@ -0,0 +1,220 @@
+use num::Integer;
+use utils::Result;
+
+use crate::network::Network;
+use crate::Direction;
+
+/// Everything about a ghost's walk that matters for when it's on a goal.
+///
+/// A walk's state is its node plus its index into the directions, so it
+/// must repeat eventually. After `prefix` steps the ghost enters a cycle of
+/// `cycle` steps and repeats it forever.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GhostPath {
+    pub prefix: usize,
+    pub cycle: usize,
+    /// Goal steps before the cycle starts. Each happens once.
+    pub pre_goals: Vec<usize>,
+    /// Goal steps within the first trip round the cycle, each in
+    /// `prefix..prefix + cycle`. Each recurs every `cycle` steps.
+    pub cycle_goals: Vec<usize>,
+}
+
+impl GhostPath {
+    /// Walk from `start` until a state repeats, recording every step that
+    /// lands on a goal.
+    ///
+    /// States are only remembered at the start of the directions: any cycle
+    /// passes through there, so this finds the exact cycle length while
+    /// storing one entry per node rather than one per step. The prefix may
+    /// come out longer than the shortest possible, which is still correct.
+    pub fn trace(
+        network: &Network,
+        start: u32,
+        is_goal: impl Fn(u32) -> bool,
+        directions: &[Direction],
+    ) -> Result<GhostPath> {
+        anyhow::ensure!(!directions.is_empty(), "no directions");
+
+        let mut seen: Vec<Option<usize>> = vec![None; network.len()];
+        let mut goals = vec![];
+        let mut pos = start;
+        let mut steps = 0;
+        loop {
+            let index = steps % directions.len();
+            if index == 0 {
+                if let Some(prefix) = seen[pos as usize] {
+                    let (pre_goals, cycle_goals) = goals.iter().partition(|&&g| g < prefix);
+                    return Ok(GhostPath {
+                        prefix,
+                        cycle: steps - prefix,
+                        pre_goals,
+                        cycle_goals,
+                    });
+                }
+                seen[pos as usize] = Some(steps);
+            }
+
+            if steps > 0 && is_goal(pos) {
+                goals.push(steps);
+            }
+            pos = network.step(pos, directions[index]);
+            steps += 1;
+        }
+    }
+
+    /// Is the ghost on a goal after `steps` steps?
+    pub fn is_goal_at(&self, steps: usize) -> bool {
+        if steps < self.prefix {
+            return self.pre_goals.contains(&steps);
+        }
+        let offset = self.prefix + (steps - self.prefix) % self.cycle;
+        self.cycle_goals.contains(&offset)
+    }
+
+    /// The first step the ghost is on a goal, if it ever is.
+    pub fn first_goal(&self) -> Option<usize> {
+        self.pre_goals.first().or(self.cycle_goals.first()).copied()
+    }
+
+    /// Whether this ghost is on a goal at exactly the multiples of its first
+    /// goal step, which is what taking the LCM of first goals assumes.
+    pub fn lcm_safe(&self) -> bool {
+        let Some(first) = self.first_goal() else {
+            return false;
+        };
+        self.cycle.is_multiple_of(first)
+            && (1..self.prefix + self.cycle).all(|t| self.is_goal_at(t) == t.is_multiple_of(first))
+    }
+}
+
+/// The first step at which every ghost is on a goal at the same time.
+pub fn first_common_goal(paths: &[GhostPath]) -> Option<usize> {
+    let all_goals = |t: usize| paths.iter().all(|p| p.is_goal_at(t));
+
+    // Any common goal before every ghost is cycling has to be a pre-cycle
+    // goal of whichever ghost starts cycling last.
+    let early = paths
+        .iter()
+        .flat_map(|p| p.pre_goals.iter().copied())
+        .filter(|&t| all_goals(t))
+        .min();
+    if early.is_some() {
+        return early;
+    }
+
+    // Otherwise it's a solution to one congruence per ghost, for some choice
+    // of goal offset per ghost.
+    let mut solutions: Vec<(i128, i128)> = vec![(0, 1)];
+    for path in paths {
+        let cycle = path.cycle as i128;
+        solutions = solutions
+            .iter()
+            .flat_map(|&sol| {
+                path.cycle_goals
+                    .iter()
+                    .filter_map(move |&g| crt(sol, (g as i128 % cycle, cycle)))
+            })
+            .collect();
+        solutions.sort_unstable();
+        solutions.dedup();
+    }
+
+    let start = paths.iter().map(|p| p.prefix).max().unwrap_or(0).max(1) as i128;
+    solutions
+        .into_iter()
+        .map(|(r, m)| start + (r - start).rem_euclid(m))
+        .min()
+        .map(|t| t as usize)
+}
+
+/// Combine `x = a1 (mod m1)` and `x = a2 (mod m2)` into a single congruence
+/// mod `lcm(m1, m2)`, if they have a common solution. The moduli need not be
+/// coprime.
+pub fn crt((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let egcd = m1.extended_gcd(&m2);
+    let g = egcd.gcd;
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+
+    // m1 * egcd.x = g (mod m2), so stepping a1 by m1 * k, with k solving
+    // m1 * k = a2 - a1 (mod m2), lands on a2.
+    let lcm = m1 / g * m2;
+    let k = ((a2 - a1) / g * egcd.x).rem_euclid(m2 / g);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((1, 4), (2, 6)), None);
+        assert_eq!(crt((0, 1), (5, 7)), Some((5, 7)));
+    }
+
+    #[test]
+    fn test_first_common_goal() {
+        // The LCM-friendly case: goals at multiples of 2 and 3.
+        let a = GhostPath {
+            prefix: 1,
+            cycle: 2,
+            pre_goals: vec![],
+            cycle_goals: vec![2],
+        };
+        let b = GhostPath {
+            prefix: 1,
+            cycle: 3,
+            pre_goals: vec![],
+            cycle_goals: vec![3],
+        };
+        assert!(a.lcm_safe() && b.lcm_safe());
+        assert_eq!(first_common_goal(&[a.clone(), b.clone()]), Some(6));
+
+        // Goals at 5, 12, 19, ... and 3, 7, 11, ...: the LCM of first goals
+        // (15) is wrong, the real answer is 19.
+        let c = GhostPath {
+            prefix: 2,
+            cycle: 7,
+            pre_goals: vec![],
+            cycle_goals: vec![5],
+        };
+        let d = GhostPath {
+            prefix: 0,
+            cycle: 4,
+            pre_goals: vec![],
+            cycle_goals: vec![3],
+        };
+        assert!(!c.lcm_safe() && !d.lcm_safe());
+        assert_eq!(first_common_goal(&[c.clone(), d.clone()]), Some(19));
+
+        // A goal before the cycle that happens to line up.
+        let e = GhostPath {
+            prefix: 10,
+            cycle: 5,
+            pre_goals: vec![4],
+            cycle_goals: vec![11],
+        };
+        assert_eq!(first_common_goal(&[d.clone(), e.clone()]), Some(11));
+        let f = GhostPath {
+            prefix: 10,
+            cycle: 5,
+            pre_goals: vec![7],
+            cycle_goals: vec![11],
+        };
+        assert_eq!(first_common_goal(&[d.clone(), f]), Some(7));
+
+        // Parities never line up.
+        let g = GhostPath {
+            prefix: 0,
+            cycle: 2,
+            pre_goals: vec![],
+            cycle_goals: vec![1],
+        };
+        assert_eq!(first_common_goal(&[a, g]), None);
+    }
+}
@@ -1,30 +1,120 @@
-use std::collections::HashMap;
+mod ghost;
+mod network;
+mod structure;
 
+use ghost::GhostPath;
+use network::Network;
+use structure::MacroStep;
 use utils::{Part1, Part2, Result};
 
 struct Problem8;
 
 impl Problem8 {
+    /// Count the steps from `start` until `cond` holds.
+    ///
+    /// Fails if the walk gets back to a node at the same point in the
+    /// directions without ever meeting `cond`, since it would then loop
+    /// forever. Any such loop passes through the start of the directions,
+    /// so that's the only place the walk needs to remember where it's been.
     fn steps_to_complete(
-        mut pos: Label,
-        cond: impl Fn(Label) -> bool,
+        start: u32,
+        cond: impl Fn(u32) -> bool,
         directions: &[Direction],
-        m: &HashMap<Label, (Label, Label)>,
+        network: &Network,
     ) -> Result<usize> {
+        anyhow::ensure!(!directions.is_empty(), "no directions");
+
+        let mut seen: Vec<Option<usize>> = vec![None; network.len()];
+        let mut pos = start;
         for (i, &d) in directions.iter().cycle().enumerate() {
-            pos = match (m.get(&pos), d) {
-                (Some((left, _)), Direction::Left) => *left,
-                (Some((_, right)), Direction::Right) => *right,
-                (None, _) => anyhow::bail!("No map node for {pos:?}"),
-            };
+            if i % directions.len() == 0 {
+                if let Some(first) = seen[pos as usize] {
+                    let cycle = directions
+                        .iter()
+                        .cycle()
+                        .take(i - first)
+                        .scan(pos, |p, &d| Some(std::mem::replace(p, network.step(*p, d))))
+                        .map(|id| network.label(id))
+                        .collect::<Vec<_>>();
+                    anyhow::bail!(
+                        "{} never reaches the goal: it loops every {} steps through {}",
+                        network.label(start),
+                        i - first,
+                        describe_cycle(&cycle),
+                    );
+                }
+                seen[pos as usize] = Some(i);
+            }
+
+            pos = network.step(pos, d);
             if cond(pos) {
                 return Ok(i + 1);
             }
         }
-        unreachable!()
+        unreachable!("directions.iter().cycle() is endless")
+    }
+
+    /// Trace the walk of the ghost starting on each node ending in `A`.
+    fn ghost_paths(input: &Input, network: &Network) -> Result<Vec<(u32, GhostPath)>> {
+        let is_goal: Vec<bool> = network
+            .ids()
+            .map(|id| network.label(id).ends_with('Z'))
+            .collect();
+        network
+            .ids()
+            .filter(|&id| network.label(id).ends_with('A'))
+            .map(|start| {
+                let path =
+                    GhostPath::trace(network, start, |id| is_goal[id as usize], &input.directions)?;
+                Ok((start, path))
+            })
+            .collect()
+    }
+
+    /// The first step every ghost is on a goal together, and a description
+    /// of every ghost for which taking the LCM of first goal steps would
+    /// have given the wrong answer.
+    fn solve_ghosts(input: &Input) -> Result<(usize, Vec<String>)> {
+        let network = Network::compile(&input.nodes)?;
+        let (starts, paths): (Vec<u32>, Vec<GhostPath>) =
+            Self::ghost_paths(input, &network)?.into_iter().unzip();
+
+        let warnings = starts
+            .iter()
+            .zip(paths.iter())
+            .filter(|(_, path)| !path.lcm_safe())
+            .map(|(&start, path)| {
+                format!(
+                    "LCM of first goals is wrong for {}: first goal at {:?}, \
+                     then a cycle of {} from step {} with goals at {:?}",
+                    network.label(start),
+                    path.first_goal(),
+                    path.cycle,
+                    path.prefix,
+                    path.cycle_goals,
+                )
+            })
+            .collect();
+        let steps = ghost::first_common_goal(&paths)
+            .ok_or_else(|| anyhow::anyhow!("ghosts never all reach a goal together"))?;
+        Ok((steps, warnings))
     }
 }
 
+/// Show the nodes in a cycle, eliding the middle of long ones.
+fn describe_cycle(labels: &[&str]) -> String {
+    const SHOWN: usize = 8;
+    if labels.len() <= SHOWN {
+        return format!("{} -> {}", labels.join(" -> "), labels[0]);
+    }
+    format!(
+        "{} -> ... -> {} -> {}",
+        labels[..SHOWN / 2].join(" -> "),
+        labels[labels.len() - SHOWN / 2..].join(" -> "),
+        labels[0],
+    )
+}
+
 impl Part1 for Problem8 {
     const N: u8 = 8;
     type Input = Input;
@@ -34,51 +124,19 @@ impl Part1 for Problem8 {
     }
 
     fn run1(input: Self::Input) -> anyhow::Result<String> {
-        let mut m = HashMap::new();
-        for node in input.nodes.into_iter() {
-            m.insert(node.label, node.choices);
-        }
+        let network = Network::compile(&input.nodes)?;
+        let (Some(start), Some(goal)) = (network.id("AAA"), network.id("ZZZ")) else {
+            anyhow::bail!("network needs both AAA and ZZZ");
+        };
 
-        let steps = Self::steps_to_complete(
-            Label(['A', 'A', 'A']),
-            |l| l == Label(['Z', 'Z', 'Z']),
-            &input.directions,
-            &m,
-        )?;
+        let steps = Self::steps_to_complete(start, |id| id == goal, &input.directions, &network)?;
         Ok(steps.to_string())
     }
 }
 
 impl Part2 for Problem8 {
     fn run2(input: Self::Input) -> anyhow::Result<String> {
-        let mut m = HashMap::new();
-        for node in input.nodes.iter() {
-            m.insert(node.label, node.choices);
-        }
-
-        let pos: Vec<Label> = input
-            .nodes
-            .iter()
-            .filter_map(|n| match n.label {
-                Label([_, _, 'A']) => Some(n.label),
-                _ => None,
-            })
-            .collect();
-
-        let steps = pos
-            .iter()
-            .map(|&p| {
-                Self::steps_to_complete(p, |Label([_, _, c])| c == 'Z', &input.directions, &m)
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        let lcm = steps
-            .iter()
-            .copied()
-            .reduce(|a, b| num::integer::lcm(a, b))
-            .unwrap();
-
-        Ok(lcm.to_string())
+        Ok(Self::solve_ghosts(&input)?.0.to_string())
     }
 }
 
@@ -87,12 +145,9 @@ struct Input {
     nodes: Vec<MapNode>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
-struct Label([char; 3]);
-
 struct MapNode {
-    label: Label,
-    choices: (Label, Label),
+    label: String,
+    choices: (String, String),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -101,9 +156,19 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn label(self) -> char {
+        match self {
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+}
+
 mod parser {
-    use super::{Direction, Input, Label, MapNode};
-    use nom::bytes::complete::{self, is_a, tag};
+    use super::{Direction, Input, MapNode};
+    use nom::bytes::complete::{is_a, tag};
+    use nom::character::complete::alphanumeric1;
     use nom::multi::separated_list1;
     use nom::sequence::{self, separated_pair};
     use nom::{IResult, Parser};
@@ -122,16 +187,8 @@ mod parser {
             .parse(input)
     }
 
-    fn label(input: &str) -> IResult<&str, Label> {
-        complete::take(3usize)
-            .map(|s: &str| {
-                let mut it = s.chars();
-                let (Some(a), Some(b), Some(c)) = (it.next(), it.next(), it.next()) else {
-                    panic!("bad s: {s}")
-                };
-                Label([a, b, c])
-            })
-            .parse(input)
+    fn label(input: &str) -> IResult<&str, String> {
+        alphanumeric1.map(String::from).parse(input)
     }
 
     fn node(input: &str) -> IResult<&str, MapNode> {
@@ -152,8 +209,160 @@ mod parser {
     }
 }
 
+/// Print the network's components, dead ends and macro-step behaviour.
+fn structure_report(input: &Input) -> Result<()> {
+    let network = Network::compile(&input.nodes)?;
+    let starts: Vec<u32> = network
+        .ids()
+        .filter(|&id| network.label(id).ends_with('A'))
+        .collect();
+    let names = |ids: &[u32]| {
+        let labels: Vec<&str> = ids.iter().map(|&id| network.label(id)).collect();
+        labels.join(" ")
+    };
+
+    let components = structure::strongly_connected(&network);
+    println!(
+        "{} nodes in {} strongly connected components",
+        network.len(),
+        components.len()
+    );
+    for component in components.iter().filter(|c| c.len() > 1) {
+        println!("  {} nodes: {}", component.len(), names(component));
+    }
+
+    let unreachable = structure::unreachable_from(&network, &starts);
+    println!("{} nodes unreachable from any start", unreachable.len());
+    if !unreachable.is_empty() {
+        println!("  {}", names(&unreachable));
+    }
+
+    let loops: Vec<String> = structure::self_loops(&network)
+        .into_iter()
+        .map(|(id, d)| format!("{}:{}", network.label(id), d.label()))
+        .collect();
+    println!("{} self-loops", loops.len());
+    if !loops.is_empty() {
+        println!("  {}", loops.join(" "));
+    }
+
+    let macro_step = MacroStep::new(&network, &input.directions);
+    println!("After one pass of {} directions:", input.directions.len());
+    for &start in starts.iter() {
+        println!(
+            "  {} -> {}",
+            network.label(start),
+            network.label(macro_step.apply(start))
+        );
+    }
+    Ok(())
+}
+
+/// Print where every start is after `trips` passes of the directions.
+fn simulate(input: &Input, trips: &str) -> Result<()> {
+    let trips: u64 = trips
+        .parse()
+        .map_err(|e| anyhow::anyhow!("bad trip count {trips:?}: {e}"))?;
+    let network = Network::compile(&input.nodes)?;
+    let macro_step = MacroStep::new(&network, &input.directions);
+    for start in network.ids().filter(|&id| network.label(id).ends_with('A')) {
+        let end = macro_step.run(start, trips);
+        println!("{} -> {}", network.label(start), network.label(end));
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
+    if utils::has_flag("--dot") {
+        let network = Network::compile(&utils::load::<Problem8>()?.nodes)?;
+        print!("{}", structure::to_dot(&network));
+        return Ok(());
+    }
+    if utils::has_flag("--structure") {
+        return structure_report(&utils::load::<Problem8>()?);
+    }
+    if let Some(trips) = utils::flag_value("--simulate") {
+        return simulate(&utils::load::<Problem8>()?, &trips);
+    }
+
     utils::run_part1::<Problem8>()?;
-    utils::run_part2::<Problem8>()?;
+    // Part 2 is solved here rather than through run_part2, so the ghosts
+    // are only traced once for both the answer and the warnings.
+    let (steps, warnings) = Problem8::solve_ghosts(&utils::load::<Problem8>()?)?;
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+    utils::print_part(2, &steps.to_string());
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unreachable_goal() -> Result<()> {
+        let input = r#"
+LR
+
+AAA = (BBB, CCC)
+BBB = (AAA, DDD)
+CCC = (ZZZ, ZZZ)
+DDD = (BBB, BBB)
+ZZZ = (ZZZ, ZZZ)"#;
+        let err = Problem8::run1(Problem8::parse(input)?).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "AAA never reaches the goal: it loops every 2 steps through DDD -> BBB -> DDD"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_undefined_node() -> Result<()> {
+        let input = "L\n\nAAA = (BBB, ZZZ)\nZZZ = (ZZZ, ZZZ)";
+        let err = Problem8::run1(Problem8::parse(input)?).unwrap_err();
+        assert_eq!(err.to_string(), "AAA leads to BBB, which is never defined");
+
+        let input = "L\n\nAAA = (ZZZ, ZZZ)\nAAA = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)";
+        let err = Problem8::run1(Problem8::parse(input)?).unwrap_err();
+        assert_eq!(err.to_string(), "AAA is defined more than once");
+        Ok(())
+    }
+
+    #[test]
+    fn test_p2_example() -> Result<()> {
+        let input = r#"
+LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)"#;
+        assert_eq!(Problem8::run2(Problem8::parse(input)?)?, "6");
+        assert!(Problem8::solve_ghosts(&Problem8::parse(input)?)?
+            .1
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcm_warnings() -> Result<()> {
+        // Goals at 1, 3, 5, ..., so the LCM of first goals (1) is wrong.
+        let input = "L\n\n11A = (11Z, 11Z)\n11Z = (11B, 11B)\n11B = (11Z, 11Z)";
+        let (steps, warnings) = Problem8::solve_ghosts(&Problem8::parse(input)?)?;
+        assert_eq!(steps, 1);
+        assert_eq!(
+            warnings,
+            [
+                "LCM of first goals is wrong for 11A: first goal at Some(1), \
+              then a cycle of 2 from step 1 with goals at [1]"
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use utils::Result;
+
+use crate::{Direction, MapNode};
+
+/// A network compiled down to dense node ids.
+///
+/// Labels are interned in the order they're defined, and each node's left
+/// and right neighbours live in a flat `Vec`, so stepping is an index rather
+/// than a hash lookup.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Network {
+    labels: Vec<String>,
+    ids: HashMap<String, u32>,
+    edges: Vec<(u32, u32)>,
+}
+
+impl Network {
+    pub fn compile(nodes: &[MapNode]) -> Result<Network> {
+        let mut labels = Vec::with_capacity(nodes.len());
+        let mut ids = HashMap::with_capacity(nodes.len());
+        for node in nodes {
+            let id = u32::try_from(labels.len())?;
+            if ids.insert(node.label.clone(), id).is_some() {
+                anyhow::bail!("{} is defined more than once", node.label);
+            }
+            labels.push(node.label.clone());
+        }
+
+        let lookup = |from: &str, to: &str| {
+            ids.get(to)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("{from} leads to {to}, which is never defined"))
+        };
+        let edges = nodes
+            .iter()
+            .map(|node| {
+                let (left, right) = &node.choices;
+                Ok((lookup(&node.label, left)?, lookup(&node.label, right)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Network { labels, ids, edges })
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn id(&self, label: &str) -> Option<u32> {
+        self.ids.get(label).copied()
+    }
+
+    pub fn label(&self, id: u32) -> &str {
+        &self.labels[id as usize]
+    }
+
+    /// Every node id, in the order the nodes were defined.
+    pub fn ids(&self) -> impl Iterator<Item = u32> {
+        0..self.labels.len() as u32
+    }
+
+    /// The left and right neighbours of `id`.
+    pub fn edges(&self, id: u32) -> (u32, u32) {
+        self.edges[id as usize]
+    }
+
+    pub fn step(&self, id: u32, d: Direction) -> u32 {
+        let (left, right) = self.edges(id);
+        match d {
+            Direction::Left => left,
+            Direction::Right => right,
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write;
+
+use crate::network::Network;
+use crate::Direction;
+
+/// Render the network as a Graphviz digraph, one edge per direction.
+///
+/// When both directions lead to the same node the edges are merged into a
+/// single `LR` edge to keep the picture readable.
+pub fn to_dot(network: &Network) -> String {
+    let mut out = String::from("digraph network {\n");
+    for id in network.ids() {
+        let from = network.label(id);
+        let (left, right) = network.edges(id);
+        if left == right {
+            writeln!(
+                out,
+                "  \"{from}\" -> \"{}\" [label=\"LR\"];",
+                network.label(left)
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "  \"{from}\" -> \"{}\" [label=\"L\"];",
+                network.label(left)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "  \"{from}\" -> \"{}\" [label=\"R\"];",
+                network.label(right)
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The network's strongly connected components, each sorted by id. Larger
+/// components come first, ties broken by their smallest id.
+///
+/// This is Kosaraju's algorithm, with both passes done iteratively since
+/// real inputs have long chains that would overflow a recursive walk.
+pub fn strongly_connected(network: &Network) -> Vec<Vec<u32>> {
+    let n = network.len();
+    let successors = |id: u32| {
+        let (left, right) = network.edges(id);
+        std::iter::once(left).chain((left != right).then_some(right))
+    };
+
+    // First pass: order nodes by when their depth-first search finishes.
+    let mut finished = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    for root in network.ids() {
+        if visited[root as usize] {
+            continue;
+        }
+        visited[root as usize] = true;
+        let mut stack = vec![(root, successors(root))];
+        while let Some((id, next)) = stack.last_mut() {
+            match next.find(|&s| !visited[s as usize]) {
+                Some(s) => {
+                    visited[s as usize] = true;
+                    stack.push((s, successors(s)));
+                }
+                None => {
+                    finished.push(*id);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    // Second pass: flood the reversed graph in reverse finishing order.
+    let mut predecessors = vec![vec![]; n];
+    for id in network.ids() {
+        for s in successors(id) {
+            predecessors[s as usize].push(id);
+        }
+    }
+    let mut component = vec![None; n];
+    let mut components: Vec<Vec<u32>> = vec![];
+    for &root in finished.iter().rev() {
+        if component[root as usize].is_some() {
+            continue;
+        }
+        let mut members = vec![];
+        let mut stack = vec![root];
+        component[root as usize] = Some(components.len());
+        while let Some(id) = stack.pop() {
+            members.push(id);
+            for &p in predecessors[id as usize].iter() {
+                if component[p as usize].is_none() {
+                    component[p as usize] = Some(components.len());
+                    stack.push(p);
+                }
+            }
+        }
+        members.sort_unstable();
+        components.push(members);
+    }
+
+    components.sort_by_key(|c| (std::cmp::Reverse(c.len()), c[0]));
+    components
+}
+
+/// Nodes that no walk from any of `starts` can reach, whatever the
+/// directions.
+pub fn unreachable_from(network: &Network, starts: &[u32]) -> Vec<u32> {
+    let mut reached = vec![false; network.len()];
+    let mut stack = starts.to_vec();
+    while let Some(id) = stack.pop() {
+        if std::mem::replace(&mut reached[id as usize], true) {
+            continue;
+        }
+        let (left, right) = network.edges(id);
+        stack.extend([left, right]);
+    }
+    network.ids().filter(|&id| !reached[id as usize]).collect()
+}
+
+/// Every node and direction that leads straight back to the same node.
+pub fn self_loops(network: &Network) -> Vec<(u32, Direction)> {
+    network
+        .ids()
+        .flat_map(|id| [(id, Direction::Left), (id, Direction::Right)])
+        .filter(|&(id, d)| network.step(id, d) == id)
+        .collect()
+}
+
+/// Where each node ends up after following the whole direction string once.
+///
+/// Building this costs one pass over the directions per node, after which
+/// each trip through the directions is a single lookup.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MacroStep {
+    after: Vec<u32>,
+}
+
+impl MacroStep {
+    pub fn new(network: &Network, directions: &[Direction]) -> MacroStep {
+        let after = network
+            .ids()
+            .map(|id| directions.iter().fold(id, |pos, &d| network.step(pos, d)))
+            .collect();
+        MacroStep { after }
+    }
+
+    /// The node reached from `id` after one trip through the directions.
+    pub fn apply(&self, id: u32) -> u32 {
+        self.after[id as usize]
+    }
+
+    /// The node reached from `id` after `trips` trips through the directions.
+    ///
+    /// The macro-step map sends every node somewhere, so repeated trips from
+    /// any node end up cycling. Once the cycle is found the remaining trips
+    /// are skipped modulo its length, so this takes at most one lookup per
+    /// node however large `trips` is.
+    pub fn run(&self, id: u32, trips: u64) -> u32 {
+        let mut seen: Vec<Option<u64>> = vec![None; self.after.len()];
+        let mut pos = id;
+        for trip in 0..trips {
+            if let Some(first) = seen[pos as usize] {
+                let left = (trips - trip) % (trip - first);
+                return (0..left).fold(pos, |p, _| self.apply(p));
+            }
+            seen[pos as usize] = Some(trip);
+            pos = self.apply(pos);
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Part1, Problem8};
+
+    const INPUT: &str = r#"
+LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)
+CCC = (CCC, DDD)
+DDD = (CCC, AAA)"#;
+
+    fn labels(network: &Network, ids: impl IntoIterator<Item = u32>) -> Vec<&str> {
+        ids.into_iter().map(|id| network.label(id)).collect()
+    }
+
+    #[test]
+    fn test_structure() -> utils::Result<()> {
+        let input = Problem8::parse(INPUT)?;
+        let network = Network::compile(&input.nodes)?;
+
+        let components: Vec<Vec<&str>> = strongly_connected(&network)
+            .into_iter()
+            .map(|c| labels(&network, c))
+            .collect();
+        assert_eq!(
+            components,
+            [vec!["AAA", "BBB"], vec!["CCC", "DDD"], vec!["ZZZ"]]
+        );
+
+        let start = network.id("AAA").unwrap();
+        assert_eq!(
+            labels(&network, unreachable_from(&network, &[start])),
+            ["CCC", "DDD"]
+        );
+
+        let loops: Vec<(&str, char)> = self_loops(&network)
+            .into_iter()
+            .map(|(id, d)| (network.label(id), d.label()))
+            .collect();
+        assert_eq!(loops, [("ZZZ", 'L'), ("ZZZ", 'R'), ("CCC", 'L')]);
+
+        let dot = to_dot(&network);
+        assert!(dot.contains("\"AAA\" -> \"BBB\" [label=\"LR\"];"));
+        assert!(dot.contains("\"BBB\" -> \"ZZZ\" [label=\"R\"];"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_macro_step() -> utils::Result<()> {
+        let input = Problem8::parse(INPUT)?;
+        let network = Network::compile(&input.nodes)?;
+        let macro_step = MacroStep::new(&network, &input.directions);
+
+        for start in network.ids() {
+            let mut pos = start;
+            for trips in 0..20u64 {
+                assert_eq!(
+                    macro_step.run(start, trips),
+                    pos,
+                    "{}",
+                    network.label(start)
+                );
+                pos = input
+                    .directions
+                    .iter()
+                    .fold(pos, |p, &d| network.step(p, d));
+            }
+        }
+        Ok(())
+    }
+}
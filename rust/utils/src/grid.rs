@@ -0,0 +1,291 @@
+//! A dense 2D grid, for the puzzles whose input is a picture.
+
+use std::collections::HashSet;
+use std::ops::{Index, IndexMut, Range};
+
+use anyhow::ensure;
+
+/// A `(row, col)` position in a grid.
+pub type Pos = (usize, usize);
+
+/// Row/column offsets of the four orthogonal neighbours.
+const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// Row/column offsets of all eight neighbours, in reading order.
+const SURROUNDING: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// A rectangular grid of cells, stored row-major.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+/// A horizontal stretch of cells in a single row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Run {
+    pub row: usize,
+    pub cols: Range<usize>,
+}
+
+impl Run {
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.cols.clone().map(|col| (self.row, col))
+    }
+}
+
+impl<T> Grid<T> {
+    /// Build a grid from its rows, which must all be the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> anyhow::Result<Grid<T>> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        let mut cells = Vec::with_capacity(width * height);
+        for (i, row) in rows.into_iter().enumerate() {
+            ensure!(
+                row.len() == width,
+                "row {i} is {} cells wide, but row 0 is {width}",
+                row.len()
+            );
+            cells.extend(row);
+        }
+        Ok(Grid { width, height, cells })
+    }
+
+    /// Parse a grid from text, one row per line and one cell per `char`.
+    pub fn parse(
+        text: &str,
+        mut cell: impl FnMut(char) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Grid<T>> {
+        let rows = text
+            .lines()
+            .map(|line| line.chars().map(&mut cell).collect())
+            .collect::<anyhow::Result<_>>()?;
+        Grid::from_rows(rows)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, (row, col): Pos) -> Option<&T> {
+        if row < self.height && col < self.width {
+            Some(&self.cells[row * self.width + col])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, (row, col): Pos) -> Option<&mut T> {
+        if row < self.height && col < self.width {
+            Some(&mut self.cells[row * self.width + col])
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks() panics on zero, and an empty grid has no rows anyway.
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &T> {
+        assert!(col < self.width, "column {col} out of bounds");
+        self.cells.iter().skip(col).step_by(self.width)
+    }
+
+    /// Every position in the grid, in reading order.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| (row, col)))
+    }
+
+    /// Every cell with its position, in reading order.
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    /// The orthogonal neighbours of `pos` that are inside the grid.
+    pub fn neighbors4(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        self.offsets(pos, &ORTHOGONAL)
+    }
+
+    /// All eight neighbours of `pos` that are inside the grid, diagonals
+    /// included.
+    pub fn neighbors8(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        self.offsets(pos, &SURROUNDING)
+    }
+
+    fn offsets(
+        &self,
+        (row, col): Pos,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = Pos> {
+        let (width, height) = (self.width, self.height);
+        offsets.iter().filter_map(move |&(dr, dc)| {
+            let r = row.checked_add_signed(dr).filter(|&r| r < height)?;
+            let c = col.checked_add_signed(dc).filter(|&c| c < width)?;
+            Some((r, c))
+        })
+    }
+
+    /// Every maximal horizontal run of cells matching `pred`, in reading
+    /// order.
+    pub fn runs(&self, pred: impl Fn(&T) -> bool) -> Vec<Run> {
+        let mut runs = vec![];
+        for (row, cells) in self.rows().enumerate().take(self.height) {
+            let mut start = None;
+            for (col, cell) in cells.iter().enumerate() {
+                match (pred(cell), start) {
+                    (true, None) => start = Some(col),
+                    (false, Some(s)) => {
+                        runs.push(Run { row, cols: s..col });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(s) = start {
+                runs.push(Run { row, cols: s..self.width });
+            }
+        }
+        runs
+    }
+
+    /// The orthogonally connected region of cells matching `pred` that
+    /// contains `start`, in the order it was flooded. Empty if `start`
+    /// itself doesn't match.
+    pub fn region(&self, start: Pos, pred: impl Fn(&T) -> bool) -> Vec<Pos> {
+        if !self.get(start).is_some_and(&pred) {
+            return vec![];
+        }
+        let mut seen = HashSet::from([start]);
+        let mut region = vec![];
+        let mut stack = vec![start];
+        while let Some(pos) = stack.pop() {
+            region.push(pos);
+            for next in self.neighbors4(pos) {
+                if pred(&self[next]) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Apply `f` to every cell, keeping the shape.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Draw the grid as text, one line per row.
+    pub fn render(&self, mut cell: impl FnMut(Pos, &T) -> char) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for (row, cells) in self.rows().enumerate().take(self.height) {
+            out.extend(cells.iter().enumerate().map(|(col, c)| cell((row, col), c)));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos).unwrap_or_else(|| {
+            panic!(
+                "{pos:?} out of bounds for {}x{} grid",
+                self.height, self.width
+            )
+        })
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        let (height, width) = (self.height, self.width);
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("{pos:?} out of bounds for {height}x{width} grid"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(text: &str) -> Grid<char> {
+        Grid::parse(text, Ok).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_index() {
+        let grid = chars("ab.\n.cd");
+        assert_eq!((grid.height(), grid.width()), (2, 3));
+        assert_eq!(grid[(1, 2)], 'd');
+        assert_eq!(grid.get((2, 0)), None);
+        assert_eq!(grid.get((0, 3)), None);
+        assert_eq!(grid.row(1), ['.', 'c', 'd']);
+        assert_eq!(grid.column(1).collect::<String>(), "bc");
+        assert_eq!(grid.render(|_, &c| c), "ab.\n.cd\n");
+
+        let err = Grid::parse("abc\nde", Ok).unwrap_err();
+        assert_eq!(err.to_string(), "row 1 is 2 cells wide, but row 0 is 3");
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = chars("...\n...\n...");
+        assert_eq!(grid.neighbors4((1, 1)).count(), 4);
+        assert_eq!(grid.neighbors8((1, 1)).count(), 8);
+        assert_eq!(
+            grid.neighbors8((0, 0)).collect::<Vec<_>>(),
+            [(0, 1), (1, 0), (1, 1)]
+        );
+        assert_eq!(
+            grid.neighbors4((2, 2)).collect::<Vec<_>>(),
+            [(1, 2), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_runs_and_regions() {
+        let grid = chars("12.3\n..45\n6...");
+        let digit = |c: &char| c.is_ascii_digit();
+        assert_eq!(
+            grid.runs(digit),
+            [
+                Run { row: 0, cols: 0..2 },
+                Run { row: 0, cols: 3..4 },
+                Run { row: 1, cols: 2..4 },
+                Run { row: 2, cols: 0..1 },
+            ]
+        );
+
+        let mut region = grid.region((0, 3), digit);
+        region.sort();
+        assert_eq!(region, [(0, 3), (1, 2), (1, 3)]);
+        assert!(grid.region((0, 2), digit).is_empty());
+    }
+}
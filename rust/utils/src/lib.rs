@@ -1,3 +1,4 @@
+pub mod grid;
 pub mod parse;
 
 use anyhow::Context;
@@ -16,20 +17,6 @@ pub fn map_fixed<const N: usize, I, O>(input: [I; N], f: impl Fn(&I) -> O) -> [O
     std::array::from_fn(move |i| f(&input[i]))
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test_take_fixed() {
-        let [one, two] = take_fixed([1, 2, 3].into_iter());
-        assert_eq!([one, two], [Some(1), Some(2)]);
-
-        let items = take_fixed::<4, _>([1, 2, 3].into_iter());
-        assert_eq!(items, [Some(1), Some(2), Some(3), None]);
-    }
-}
-
 pub fn input_path(n: u8) -> PathBuf {
     let mut p = PathBuf::from("/home/ssanderson/projects/aoc-2023/problems/");
     p.push(n.to_string());
@@ -50,10 +37,32 @@ pub trait Part2: Part1 {
     fn run2(input: Self::Input) -> anyhow::Result<String>;
 }
 
+/// Read the input for problem `n`.
+pub fn read_input(n: u8) -> anyhow::Result<String> {
+    let p = input_path(n);
+    std::fs::read_to_string(&p).with_context(|| format!("Failed to read {}", p.display()))
+}
+
+/// Read and parse the input for a problem, for runners that want to do more
+/// than print the answers.
+pub fn load<T: Part1>() -> anyhow::Result<T::Input> {
+    T::parse(&read_input(T::N)?)
+}
+
+/// Check whether a flag like `--report` was passed on the command line.
+pub fn has_flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// The value following a flag like `--bag`, if the flag was passed.
+pub fn flag_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == name)?;
+    args.next()
+}
+
 pub fn run_part1<T: Part1>() -> anyhow::Result<()> {
-    let p = input_path(T::N);
-    let data =
-        std::fs::read_to_string(&p).with_context(|| format!("Failed to read {}", p.display()))?;
+    let data = read_input(T::N)?;
 
     println!("============= Part 1 ============= ");
     let parsed = T::parse(&data)?;
@@ -65,9 +74,7 @@ pub fn run_part1<T: Part1>() -> anyhow::Result<()> {
 }
 
 pub fn run_part2<T: Part2>() -> anyhow::Result<()> {
-    let p = input_path(T::N);
-    let data =
-        std::fs::read_to_string(&p).with_context(|| format!("Failed to read {}", p.display()))?;
+    let data = read_input(T::N)?;
 
     println!("============= Part 2 ============= ");
     let parsed = T::parse(&data)?;
@@ -77,3 +84,25 @@ pub fn run_part2<T: Part2>() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Print a result in the same frame as [`run_part1`] and [`run_part2`], for
+/// runners that work the answer out themselves.
+pub fn print_part(part: u8, result: &str) {
+    println!("============= Part {part} ============= ");
+    println!("Result: {}", result);
+    println!("=========== End Part {part} =========== ");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_take_fixed() {
+        let [one, two] = take_fixed([1, 2, 3].into_iter());
+        assert_eq!([one, two], [Some(1), Some(2)]);
+
+        let items = take_fixed::<4, _>([1, 2, 3].into_iter());
+        assert_eq!(items, [Some(1), Some(2), Some(3), None]);
+    }
+}
@@ -1,6 +1,6 @@
 use nom::character::complete::{digit1, multispace1};
 use nom::combinator::map_res;
-use nom::error::Error;
+use nom::error::{Error, VerboseError, VerboseErrorKind};
 use nom::multi::separated_list1;
 use nom::{Finish, IResult};
 use std::str::FromStr;
@@ -21,3 +21,44 @@ pub fn finalize<T>(result: IResult<&str, T>) -> anyhow::Result<T> {
         Err(Error { input, code }) => Err(Error { input: input.to_string(), code }),
     }?)
 }
+
+/// Results for parsers that report what they expected when they fail.
+pub type VerboseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+/// Parse a number into an integer type, with verbose errors.
+pub fn num_verbose<T: FromStr>(input: &str) -> VerboseResult<'_, T> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// Finish parsing `input`, turning a failure into an error that names the
+/// column where parsing stopped and the innermost `context` that was being
+/// parsed there, like `column 9: expected color, found ' '`.
+pub fn finalize_verbose<'a, T>(input: &'a str, result: VerboseResult<'a, T>) -> anyhow::Result<T> {
+    let error = match result {
+        Ok((_, value)) => return Ok(value),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e,
+        Err(nom::Err::Incomplete(_)) => anyhow::bail!("incomplete input"),
+    };
+
+    let innermost = error
+        .errors
+        .iter()
+        .find(|(_, kind)| matches!(kind, VerboseErrorKind::Context(_)))
+        .or(error.errors.first());
+    let Some(&(rest, ref kind)) = innermost else {
+        anyhow::bail!("parse error");
+    };
+
+    let offset = input.len() - rest.len();
+    let column = input[..offset].chars().count() + 1;
+    let expected = match kind {
+        VerboseErrorKind::Context(context) => context.to_string(),
+        VerboseErrorKind::Char(c) => format!("{c:?}"),
+        VerboseErrorKind::Nom(kind) => kind.description().to_lowercase(),
+    };
+    let found = match rest.chars().next() {
+        Some(c) => format!("{c:?}"),
+        None => "end of input".to_string(),
+    };
+    anyhow::bail!("column {column}: expected {expected}, found {found}")
+}